@@ -0,0 +1,375 @@
+use std::borrow::Cow;
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+
+const DEFAULT_PORT: u16 = 5037;
+const PORT_ENV: &str = "ANDROID_ADB_SERVER_PORT";
+//Time to wait for response of server, so that hung server is not waited forever.
+const TIMEOUT: core::time::Duration = core::time::Duration::from_secs(10);
+
+//Packet ids of shell protocol v2
+const SHELL_STDOUT: u8 = 1;
+const SHELL_STDERR: u8 = 2;
+const SHELL_EXIT: u8 = 3;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+///Device to connect to.
+pub enum Transport {
+    ///Device with specified serial.
+    Serial(String),
+    ///Single USB device, i.e. `adb -d`
+    Usb,
+    ///Single TCP/IP device, i.e. `adb -e`
+    Local,
+    ///Single device of any kind.
+    Any,
+}
+
+impl Transport {
+    fn service(&self) -> String {
+        match self {
+            Transport::Serial(serial) => format!("host:transport:{}", serial),
+            Transport::Usb => "host:transport-usb".to_owned(),
+            Transport::Local => "host:transport-local".to_owned(),
+            Transport::Any => "host:transport-any".to_owned(),
+        }
+    }
+
+    fn wait_service(&self) -> String {
+        match self {
+            Transport::Serial(serial) => format!("host-serial:{}:wait-for-any-device", serial),
+            Transport::Usb => "host-usb:wait-for-usb-device".to_owned(),
+            Transport::Local => "host-local:wait-for-local-device".to_owned(),
+            Transport::Any => "host:wait-for-any-device".to_owned(),
+        }
+    }
+}
+
+///Quotes argument for device shell, unless it has no special characters.
+pub fn quote(arg: &str) -> Cow<'_, str> {
+    let is_safe = !arg.is_empty() && arg.bytes().all(|byte| byte.is_ascii_alphanumeric() || b"-_=+.,:/@%".contains(&byte));
+    match is_safe {
+        true => Cow::Borrowed(arg),
+        false => Cow::Owned(format!("'{}'", arg.replace('\'', "'\\''"))),
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+///Output of shell command.
+pub struct ShellOutput {
+    ///Exit code, if known.
+    pub status: Option<i32>,
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+}
+
+impl ShellOutput {
+    #[inline]
+    ///Returns whether command succeeded, assuming so if exit code is unknown.
+    pub fn is_success(&self) -> bool {
+        self.status.unwrap_or(0) == 0
+    }
+}
+
+impl From<std::process::Output> for ShellOutput {
+    #[inline]
+    fn from(output: std::process::Output) -> Self {
+        Self {
+            status: output.status.code(),
+            stdout: output.stdout,
+            stderr: output.stderr,
+        }
+    }
+}
+
+fn read_hex_len(stream: &mut TcpStream) -> io::Result<usize> {
+    let mut len = [0; 4];
+    stream.read_exact(&mut len)?;
+
+    match core::str::from_utf8(&len).ok().and_then(|len| usize::from_str_radix(len, 16).ok()) {
+        Some(len) => Ok(len),
+        None => Err(io::Error::new(io::ErrorKind::InvalidData, "invalid length of adb message")),
+    }
+}
+
+fn read_message(stream: &mut TcpStream) -> io::Result<String> {
+    let len = read_hex_len(stream)?;
+    let mut message = vec![0; len];
+    stream.read_exact(&mut message)?;
+    Ok(String::from_utf8_lossy(&message).into_owned())
+}
+
+///Sends request, expecting `OKAY` in response.
+fn request(stream: &mut TcpStream, request: &str) -> io::Result<()> {
+    //Written at once, as formatting writes in pieces.
+    stream.write_all(format!("{:04x}{}", request.len(), request).as_bytes())?;
+    read_status(stream, request)
+}
+
+fn read_status(stream: &mut TcpStream, request: &str) -> io::Result<()> {
+    let mut status = [0; 4];
+    stream.read_exact(&mut status)?;
+    match &status {
+        b"OKAY" => Ok(()),
+        b"FAIL" => {
+            let message = read_message(stream)?;
+            Err(io::Error::new(io::ErrorKind::Other, format!("adb {}: {}", request, message)))
+        },
+        _ => Err(io::Error::new(io::ErrorKind::InvalidData, format!("adb {}: unexpected response", request))),
+    }
+}
+
+///Client of adb server, talking to it over smart-socket protocol.
+pub struct Client {
+    addr: String,
+}
+
+impl Client {
+    #[inline]
+    ///Creates client of server at `addr`, e.g. `127.0.0.1:5037`
+    pub fn new(addr: String) -> Self {
+        Self {
+            addr,
+        }
+    }
+
+    fn connect(&self) -> io::Result<TcpStream> {
+        let addr = match self.addr.to_socket_addrs()?.next() {
+            Some(addr) => addr,
+            None => return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("invalid adb server address {}", self.addr))),
+        };
+
+        let stream = TcpStream::connect_timeout(&addr, TIMEOUT)?;
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        stream.set_write_timeout(Some(TIMEOUT))?;
+        Ok(stream)
+    }
+
+    ///Returns output of `host:devices`, which is the same as `adb devices` without header.
+    pub fn devices(&self) -> io::Result<String> {
+        let mut stream = self.connect()?;
+        request(&mut stream, "host:devices")?;
+        read_message(&mut stream)
+    }
+
+    fn open(&self, transport: &Transport) -> io::Result<TcpStream> {
+        let mut stream = self.connect()?;
+        request(&mut stream, &transport.service())?;
+        Ok(stream)
+    }
+
+    fn read_shell_v2(mut stream: TcpStream) -> io::Result<ShellOutput> {
+        let mut result = ShellOutput::default();
+
+        let mut header = [0; 5];
+        loop {
+            match stream.read_exact(&mut header) {
+                Ok(()) => (),
+                Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(error) => return Err(error),
+            }
+
+            let len = u32::from_le_bytes([header[1], header[2], header[3], header[4]]) as usize;
+            let mut data = vec![0; len];
+            stream.read_exact(&mut data)?;
+
+            match header[0] {
+                SHELL_STDOUT => result.stdout.extend_from_slice(&data),
+                SHELL_STDERR => result.stderr.extend_from_slice(&data),
+                SHELL_EXIT => {
+                    result.status = data.first().map(|code| i32::from(*code));
+                    break;
+                },
+                _ => (),
+            }
+        }
+
+        Ok(result)
+    }
+
+    ///Runs shell command on device, using shell protocol when device supports it.
+    ///
+    ///Without shell protocol exit code is unknown and stderr is merged into stdout.
+    pub fn shell(&self, transport: &Transport, command: &str) -> io::Result<ShellOutput> {
+        let mut stream = self.open(transport)?;
+        if request(&mut stream, &format!("shell,v2,raw:{}", command)).is_ok() {
+            return Self::read_shell_v2(stream);
+        }
+
+        //Connection is closed by server on failure.
+        let mut stream = self.open(transport)?;
+        request(&mut stream, &format!("shell:{}", command))?;
+        let mut result = ShellOutput::default();
+        stream.read_to_end(&mut result.stdout)?;
+        Ok(result)
+    }
+
+    ///Starts command on device, returning stream of its raw output, which is not limited by timeout.
+    ///
+    ///Command is started by `exec:` service, falling back to `shell:` on devices that do not support it.
+    ///Shutting down stream terminates command.
+    pub fn stream(&self, transport: &Transport, command: &str) -> io::Result<TcpStream> {
+        let mut stream = self.open(transport)?;
+        if request(&mut stream, &format!("exec:{}", command)).is_err() {
+            stream = self.open(transport)?;
+            request(&mut stream, &format!("shell:{}", command))?;
+        }
+
+        stream.set_read_timeout(None)?;
+        Ok(stream)
+    }
+
+    ///Waits until device is connected, i.e. `adb wait-for-device`
+    pub fn wait_for_device(&self, transport: &Transport) -> io::Result<()> {
+        let service = transport.wait_service();
+        let mut stream = self.connect()?;
+        request(&mut stream, &service)?;
+
+        //Server confirms request and then responds again, once device is connected.
+        stream.set_read_timeout(None)?;
+        read_status(&mut stream, &service)
+    }
+}
+
+impl Default for Client {
+    ///Creates client of local server, using port from `ANDROID_ADB_SERVER_PORT` if set.
+    fn default() -> Self {
+        let port = match std::env::var(PORT_ENV).ok().and_then(|port| port.parse().ok()) {
+            Some(port) => port,
+            None => DEFAULT_PORT,
+        };
+        Self::new(format!("127.0.0.1:{}", port))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{quote, Client, Transport};
+
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream};
+
+    fn read_request(stream: &mut TcpStream) -> String {
+        let mut len = [0; 4];
+        stream.read_exact(&mut len).expect("To read length");
+        let len = usize::from_str_radix(core::str::from_utf8(&len).unwrap(), 16).expect("Valid length");
+        let mut request = vec![0; len];
+        stream.read_exact(&mut request).expect("To read request");
+        String::from_utf8(request).unwrap()
+    }
+
+    fn write_fail(stream: &mut TcpStream, message: &str) {
+        write!(stream, "FAIL{:04x}{}", message.len(), message).expect("To write");
+    }
+
+    //Serves connections in order, recording requests.
+    fn fake_server(connections: usize) -> (String, std::thread::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("To bind");
+        let addr = listener.local_addr().unwrap().to_string();
+
+        let server = std::thread::spawn(move || {
+            let mut requests = Vec::new();
+            for _ in 0..connections {
+                let (mut stream, _) = listener.accept().expect("To accept");
+                let request = read_request(&mut stream);
+                requests.push(request.clone());
+
+                if request == "host:devices" {
+                    let devices = "emulator-5554\tdevice\n";
+                    write!(stream, "OKAY{:04x}{}", devices.len(), devices).unwrap();
+                    continue;
+                } else if request == "host-serial:emulator-5554:wait-for-any-device" {
+                    stream.write_all(b"OKAYOKAY").unwrap();
+                    continue;
+                } else if request != "host:transport:emulator-5554" {
+                    write_fail(&mut stream, "device not found");
+                    continue;
+                }
+                stream.write_all(b"OKAY").unwrap();
+
+                let request = read_request(&mut stream);
+                requests.push(request.clone());
+                if request == "shell,v2,raw:pidof com.foo" {
+                    stream.write_all(b"OKAY").unwrap();
+                    stream.write_all(&[1, 5, 0, 0, 0]).unwrap();
+                    stream.write_all(b"1200\n").unwrap();
+                    stream.write_all(&[3, 1, 0, 0, 0, 0]).unwrap();
+                } else if request == "shell:ps" {
+                    stream.write_all(b"OKAY  PID NAME\n 1200 com.foo\n").unwrap();
+                } else if request == "exec:logcat -d '*:W'" {
+                    stream.write_all(b"OKAY\x00\x01binary\n").unwrap();
+                } else if request == "shell:logcat" {
+                    stream.write_all(b"OKAYtext\n").unwrap();
+                } else {
+                    write_fail(&mut stream, "closed");
+                }
+            }
+            requests
+        });
+
+        (addr, server)
+    }
+
+    #[test]
+    fn should_talk_to_adb_server() {
+        let (addr, server) = fake_server(10);
+        let client = Client::new(addr);
+
+        assert_eq!(client.devices().expect("To list devices"), "emulator-5554\tdevice\n");
+
+        let transport = Transport::Serial("emulator-5554".to_owned());
+        let output = client.shell(&transport, "pidof com.foo").expect("To run shell");
+        assert_eq!(output.status, Some(0));
+        assert_eq!(output.stdout, b"1200\n");
+
+        //Falls back to legacy shell, if shell protocol is rejected.
+        let output = client.shell(&transport, "ps").expect("To run shell");
+        assert_eq!(output.status, None);
+        assert!(output.is_success());
+        assert_eq!(output.stdout, b"  PID NAME\n 1200 com.foo\n");
+
+        let error = client.devices().and_then(|_| client.shell(&Transport::Usb, "ps")).unwrap_err();
+        assert!(error.to_string().contains("device not found"), "{}", error);
+
+        client.wait_for_device(&transport).expect("To wait for device");
+
+        let mut output = Vec::new();
+        let mut stream = client.stream(&transport, &format!("logcat -d {}", quote("*:W"))).expect("To start logcat");
+        stream.read_to_end(&mut output).expect("To read logcat");
+        assert_eq!(output, b"\x00\x01binary\n");
+
+        //Falls back to shell, if exec is not supported.
+        output.clear();
+        let mut stream = client.stream(&transport, "logcat").expect("To start logcat");
+        stream.read_to_end(&mut output).expect("To read logcat");
+        assert_eq!(output, b"text\n");
+
+        assert_eq!(server.join().unwrap(), [
+            "host:devices",
+            "host:transport:emulator-5554",
+            "shell,v2,raw:pidof com.foo",
+            "host:transport:emulator-5554",
+            "shell,v2,raw:ps",
+            "host:transport:emulator-5554",
+            "shell:ps",
+            "host:devices",
+            "host:transport-usb",
+            "host-serial:emulator-5554:wait-for-any-device",
+            "host:transport:emulator-5554",
+            "exec:logcat -d '*:W'",
+            "host:transport:emulator-5554",
+            "exec:logcat",
+            "host:transport:emulator-5554",
+            "shell:logcat",
+        ]);
+    }
+
+    #[test]
+    fn should_quote_shell_args() {
+        assert_eq!(quote("--pid=1200"), "--pid=1200");
+        assert_eq!(quote("*:S"), "'*:S'");
+        assert_eq!(quote("12-02 10:00:00.000"), "'12-02 10:00:00.000'");
+        assert_eq!(quote("it's"), "'it'\\''s'");
+        assert_eq!(quote(""), "''");
+    }
+}
@@ -0,0 +1,319 @@
+use std::borrow::Cow;
+use std::io::{self, Read};
+
+//Size of `logger_entry` v1, which has padding instead of header size.
+const V1_HEADER_SIZE: usize = 20;
+//Header size of v2 (with euid) and v3 (with lid).
+const V2_HEADER_SIZE: usize = 24;
+//Header size of v4 (with lid and uid), anything in between is malformed.
+const V4_HEADER_SIZE: usize = 28;
+//Anything bigger is not logcat binary output.
+const MAX_HEADER_SIZE: usize = 128;
+const LOG_ID_MAX: u32 = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///Logcat buffer, identified by `lid` of entry.
+pub enum Buffer {
+    Main,
+    Radio,
+    Events,
+    System,
+    Crash,
+    Stats,
+    Security,
+    Kernel,
+}
+
+impl Buffer {
+    ///Returns buffer by its id, if known.
+    pub fn from_id(id: u32) -> Option<Self> {
+        match id {
+            0 => Some(Buffer::Main),
+            1 => Some(Buffer::Radio),
+            2 => Some(Buffer::Events),
+            3 => Some(Buffer::System),
+            4 => Some(Buffer::Crash),
+            5 => Some(Buffer::Stats),
+            6 => Some(Buffer::Security),
+            7 => Some(Buffer::Kernel),
+            _ => None,
+        }
+    }
+
+    ///Returns name of buffer, as accepted by `logcat -b`
+    pub fn name(self) -> &'static str {
+        match self {
+            Buffer::Main => "main",
+            Buffer::Radio => "radio",
+            Buffer::Events => "events",
+            Buffer::System => "system",
+            Buffer::Crash => "crash",
+            Buffer::Stats => "stats",
+            Buffer::Security => "security",
+            Buffer::Kernel => "kernel",
+        }
+    }
+
+    #[inline]
+    ///Returns whether entries of buffer have binary payload instead of text.
+    pub fn is_binary(self) -> bool {
+        match self {
+            Buffer::Events | Buffer::Stats | Buffer::Security => true,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+///Text of entry.
+pub struct Text<'a> {
+    ///Single letter, as in text output of logcat.
+    pub level: &'static str,
+    pub tag: Cow<'a, str>,
+    ///Can span multiple lines.
+    pub msg: Cow<'a, str>,
+    ///Decoded event, if entry is of binary buffer.
+    pub event: Option<crate::events::Event>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+///Entry of binary logcat output, i.e. `logger_entry` followed by payload.
+pub struct Entry {
+    pub pid: u32,
+    pub tid: u32,
+    ///Seconds since Epoch.
+    pub sec: u32,
+    pub nsec: u32,
+    ///Source buffer, unknown for v1 and v2 headers.
+    ///
+    ///Headers of v2 and v3 cannot be told apart, so `euid` below 8 (e.g. root) of v2 header is
+    ///mistaken for buffer, leaving `uid` unknown.
+    pub buffer: Option<Buffer>,
+    ///Uid (euid for v2 header), unknown for v1 and v3 headers.
+    pub uid: Option<u32>,
+    pub payload: Vec<u8>,
+}
+
+#[inline(always)]
+fn read_u32(bytes: &[u8], idx: usize) -> u32 {
+    u32::from_le_bytes([bytes[idx], bytes[idx + 1], bytes[idx + 2], bytes[idx + 3]])
+}
+
+fn level_name(priority: u8) -> &'static str {
+    match priority {
+        2 => "V",
+        3 => "D",
+        4 => "I",
+        5 => "W",
+        6 => "E",
+        7 => "F",
+        8 => "S",
+        _ => "?",
+    }
+}
+
+//Returns text until NUL and remaining bytes.
+fn split_nul(bytes: &[u8]) -> (&[u8], &[u8]) {
+    match bytes.iter().position(|byte| *byte == 0) {
+        Some(idx) => (&bytes[..idx], &bytes[idx + 1..]),
+        None => (bytes, &[]),
+    }
+}
+
+impl Entry {
+    ///Returns timestamp in format accepted by `logcat -T`, i.e. seconds since Epoch with nanoseconds.
+    pub fn timestamp(&self) -> String {
+        format!("{}.{:09}", self.sec, self.nsec)
+    }
+
+    ///Returns time of entry at specified offset.
+    pub fn datetime(&self, offset: time::UtcOffset) -> time::OffsetDateTime {
+        let datetime = match time::OffsetDateTime::from_unix_timestamp(i64::from(self.sec)) {
+            Ok(datetime) => datetime,
+            Err(_) => time::OffsetDateTime::UNIX_EPOCH,
+        };
+        (datetime + time::Duration::nanoseconds(i64::from(self.nsec))).to_offset(offset)
+    }
+
+    ///Decodes text of entry.
+    ///
+    ///Text buffers have payload of priority, tag and message, separated by NUL.
+    ///Binary payload of events is decoded using `tags`, falling back to tag number with hex of data if it is malformed.
+    pub fn text(&self, tags: Option<&crate::events::EventTags>) -> Text<'_> {
+        if self.buffer.map(Buffer::is_binary).unwrap_or(false) && self.payload.len() >= 4 {
+            if let Some(event) = crate::events::decode(&self.payload, tags) {
+                return Text {
+                    level: "I",
+                    tag: Cow::Owned(event.name.clone()),
+                    msg: Cow::Owned(event.to_string()),
+                    event: Some(event),
+                };
+            }
+
+            let data = &self.payload[4..];
+            let mut msg = String::with_capacity(data.len() * 2);
+            for byte in data {
+                msg.push_str(&format!("{:02x}", byte));
+            }
+
+            return Text {
+                level: "I",
+                tag: Cow::Owned(read_u32(&self.payload, 0).to_string()),
+                msg: Cow::Owned(msg),
+                event: None,
+            };
+        }
+
+        let (level, payload) = match self.payload.split_first() {
+            Some((priority, payload)) => (level_name(*priority), payload),
+            None => ("?", &[][..]),
+        };
+        let (tag, payload) = split_nul(payload);
+        let (msg, _) = split_nul(payload);
+
+        let msg = match String::from_utf8_lossy(msg) {
+            Cow::Borrowed(msg) => Cow::Borrowed(msg.trim_end_matches('\n')),
+            Cow::Owned(msg) => Cow::Owned(msg.trim_end_matches('\n').to_owned()),
+        };
+
+        Text {
+            level,
+            tag: String::from_utf8_lossy(tag),
+            msg,
+            event: None,
+        }
+    }
+}
+
+///Reads next entry of `logcat -B` output, returning none at the end of input.
+///
+///Supports `logger_entry` v1 to v4. Header of v2 and v3 has the same size, so its last field is
+///considered `lid` if it is valid buffer id, and `euid` otherwise.
+pub fn read_entry<R: Read>(input: &mut R) -> io::Result<Option<Entry>> {
+    let mut prefix = [0; 4];
+    let mut read = 0;
+    while read < prefix.len() {
+        match input.read(&mut prefix[read..]) {
+            Ok(0) if read == 0 => return Ok(None),
+            Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+            Ok(size) => read += size,
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => (),
+            Err(error) => return Err(error),
+        }
+    }
+
+    let len = u16::from_le_bytes([prefix[0], prefix[1]]) as usize;
+    let header_size = match u16::from_le_bytes([prefix[2], prefix[3]]) as usize {
+        0 => V1_HEADER_SIZE,
+        size if size == V1_HEADER_SIZE || size == V2_HEADER_SIZE => size,
+        size if size >= V4_HEADER_SIZE && size <= MAX_HEADER_SIZE => size,
+        size => return Err(io::Error::new(io::ErrorKind::InvalidData, format!("invalid logger_entry header size {}", size))),
+    };
+
+    let mut header = vec![0; header_size - prefix.len()];
+    input.read_exact(&mut header)?;
+
+    let (buffer, uid) = if header_size == V1_HEADER_SIZE {
+        (None, None)
+    } else if header_size == V2_HEADER_SIZE {
+        match read_u32(&header, 16) {
+            lid if lid < LOG_ID_MAX => (Buffer::from_id(lid), None),
+            euid => (None, Some(euid)),
+        }
+    } else {
+        (Buffer::from_id(read_u32(&header, 16)), Some(read_u32(&header, 20)))
+    };
+
+    let mut payload = vec![0; len];
+    input.read_exact(&mut payload)?;
+
+    Ok(Some(Entry {
+        pid: read_u32(&header, 0),
+        tid: read_u32(&header, 4),
+        sec: read_u32(&header, 8),
+        nsec: read_u32(&header, 12),
+        buffer,
+        uid,
+        payload,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_entry, Buffer, Entry};
+
+    fn header(len: usize, header_size: u16, fields: &[u32]) -> Vec<u8> {
+        let mut result = Vec::new();
+        result.extend_from_slice(&(len as u16).to_le_bytes());
+        result.extend_from_slice(&header_size.to_le_bytes());
+        for field in fields {
+            result.extend_from_slice(&field.to_le_bytes());
+        }
+        result
+    }
+
+    #[test]
+    fn should_read_entries() {
+        let payload = b"\x04ActivityManager\0Start proc 1200\nfor activity\n\0";
+        let mut input = Vec::new();
+        input.extend(header(payload.len(), 0, &[100, 101, 1_600_000_000, 123_456_789]));
+        input.extend_from_slice(payload);
+        input.extend(header(payload.len(), 24, &[100, 101, 1_600_000_000, 0, 3]));
+        input.extend_from_slice(payload);
+        input.extend(header(payload.len(), 24, &[100, 101, 1_600_000_000, 0, 10085]));
+        input.extend_from_slice(payload);
+        input.extend(header(payload.len(), 28, &[100, 101, 1_600_000_000, 0, 0, 1000]));
+        input.extend_from_slice(payload);
+        input.extend(header(8, 28, &[200, 201, 1_600_000_001, 0, 2, 1000]));
+        input.extend_from_slice(&[0x30, 0x75, 0, 0, 0, 1, 0, 0]);
+
+        let mut input = &input[..];
+        let entry = read_entry(&mut input).expect("To read").expect("To have entry");
+        assert_eq!(entry.buffer, None);
+        assert_eq!(entry.uid, None);
+        assert_eq!(entry.timestamp(), "1600000000.123456789");
+        let text = entry.text(None);
+        assert_eq!(text.level, "I");
+        assert_eq!(text.tag, "ActivityManager");
+        assert_eq!(text.msg, "Start proc 1200\nfor activity");
+
+        let entry = read_entry(&mut input).expect("To read").expect("To have entry");
+        assert_eq!((entry.buffer, entry.uid), (Some(Buffer::System), None));
+        let entry = read_entry(&mut input).expect("To read").expect("To have entry");
+        assert_eq!((entry.buffer, entry.uid), (None, Some(10085)));
+        let entry = read_entry(&mut input).expect("To read").expect("To have entry");
+        assert_eq!((entry.buffer, entry.uid), (Some(Buffer::Main), Some(1000)));
+        assert_eq!(entry.datetime(time::UtcOffset::UTC).hour(), 12);
+
+        let entry = read_entry(&mut input).expect("To read").expect("To have entry");
+        assert_eq!(entry, Entry {
+            pid: 200,
+            tid: 201,
+            sec: 1_600_000_001,
+            nsec: 0,
+            buffer: Some(Buffer::Events),
+            uid: Some(1000),
+            payload: vec![0x30, 0x75, 0, 0, 0, 1, 0, 0],
+        });
+        let text = entry.text(None);
+        assert_eq!(text.tag, "30000");
+        assert_eq!(text.msg, "00010000");
+
+        assert!(read_entry(&mut input).expect("To read").is_none());
+    }
+
+    #[test]
+    fn should_reject_text_input() {
+        let mut input = &b"12-02 24:01:13.237  555  600 I ActivityManager: Start proc"[..];
+        assert!(read_entry(&mut input).is_err());
+    }
+
+    #[test]
+    fn should_reject_truncated_header() {
+        for header_size in &[16, 21, 23, 25, 27, 129] {
+            let input = header(0, *header_size, &[100, 101, 1_600_000_000, 0, 0, 1000]);
+            let error = read_entry(&mut &input[..]).expect_err("To reject header size");
+            assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use std::io::{self, BufRead, Read, Seek};
+
+const SECTION_START: &str = "------ ";
+const SECTION_END: &str = " ------";
+const DURATION_MARKER: &str = "was the duration of '";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///Bugreport section with logcat output.
+pub enum Section {
+    System,
+    Event,
+    Radio,
+    LastLogcat,
+}
+
+impl Section {
+    #[inline]
+    ///Returns title of section within bugreport.
+    pub const fn title(&self) -> &'static str {
+        match self {
+            Section::System => "SYSTEM LOG",
+            Section::Event => "EVENT LOG",
+            Section::Radio => "RADIO LOG",
+            Section::LastLogcat => "LAST LOGCAT",
+        }
+    }
+
+    fn from_header(header: &str) -> Option<Self> {
+        [Section::System, Section::Event, Section::Radio, Section::LastLogcat].iter().find(|section| header.starts_with(section.title())).copied()
+    }
+}
+
+impl core::str::FromStr for Section {
+    type Err = ();
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        if text.eq_ignore_ascii_case("system") || text.eq_ignore_ascii_case("main") {
+            Ok(Section::System)
+        } else if text.eq_ignore_ascii_case("event") || text.eq_ignore_ascii_case("events") {
+            Ok(Section::Event)
+        } else if text.eq_ignore_ascii_case("radio") {
+            Ok(Section::Radio)
+        } else if text.eq_ignore_ascii_case("last") || text.eq_ignore_ascii_case("last-logcat") {
+            Ok(Section::LastLogcat)
+        } else {
+            Err(())
+        }
+    }
+}
+
+///Reads bugreport text, invoking `cb` for every line within logcat sections.
+pub fn read_text<R: BufRead, F: FnMut(Section, &str)>(mut input: R, mut cb: F) -> io::Result<()> {
+    let mut section = None;
+    let mut line = Vec::new();
+
+    loop {
+        line.clear();
+        if input.read_until(b'\n', &mut line)? == 0 {
+            break Ok(());
+        }
+
+        let text = String::from_utf8_lossy(&line);
+        let header = text.trim_end();
+        if let Some(header) = header.strip_prefix(SECTION_START).and_then(|header| header.strip_suffix(SECTION_END)) {
+            //Section ends either with duration line or with start of next section
+            section = match header.contains(DURATION_MARKER) {
+                true => None,
+                false => Section::from_header(header),
+            };
+            continue;
+        }
+
+        if let Some(section) = section {
+            cb(section, &text);
+        }
+    }
+}
+
+///Reads bugreport zip archive, looking up its main text file.
+pub fn read_zip<R: Read + Seek, F: FnMut(Section, &str)>(input: R, cb: F) -> io::Result<()> {
+    let mut archive = zip::ZipArchive::new(input)?;
+
+    let mut main_entry = String::new();
+    if let Ok(mut entry) = archive.by_name("main_entry.txt") {
+        entry.read_to_string(&mut main_entry)?;
+    }
+
+    let main_entry = match main_entry.trim() {
+        "" => match archive.file_names().find(|name| name.starts_with("bugreport") && name.ends_with(".txt")) {
+            Some(name) => name.to_owned(),
+            None => return Err(io::Error::new(io::ErrorKind::NotFound, "bugreport has no main text file")),
+        },
+        name => name.to_owned(),
+    };
+
+    let entry = archive.by_name(&main_entry)?;
+    read_text(io::BufReader::new(entry), cb)
+}
+
+///Reads bugreport from file, which can be either zip archive or text file.
+pub fn read_file<F: FnMut(Section, &str)>(path: &str, cb: F) -> io::Result<()> {
+    let mut file = std::fs::File::open(path)?;
+
+    let mut magic = [0u8; 4];
+    let is_zip = match file.read_exact(&mut magic) {
+        Ok(()) => magic == *b"PK\x03\x04",
+        Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => false,
+        Err(error) => return Err(error),
+    };
+    file.seek(io::SeekFrom::Start(0))?;
+
+    match is_zip {
+        true => read_zip(file, cb),
+        false => read_text(io::BufReader::new(file), cb),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_text, read_zip, Section};
+
+    const BUGREPORT: &str = "== dumpstate: 2021-12-02 24:01:13
+------ SYSTEM LOG (logcat -v threadtime -v printable -v uid -d *:v) ------
+--------- beginning of main
+12-02 24:01:13.237  1000   666   667 I flutter : system
+------ 0.123s was the duration of 'SYSTEM LOG' ------
+------ CPU INFO (top -b -n 1 -H -s 6 -o pid,tid,user,pr,ni,%cpu,s,virt,res,pcy,cmd,name) ------
+12-02 24:01:13.237  1000   666   667 I flutter : not a log
+------ EVENT LOG (logcat -b events -v threadtime -v printable -v uid -d *:v) ------
+12-02 24:01:13.237  1000   666   667 I am_proc_start: event
+------ RADIO LOG (logcat -b radio -v threadtime -v printable -v uid -d *:v) ------
+12-02 24:01:13.237  1000   666   667 I RILJ    : radio
+------ 0.001s was the duration of 'RADIO LOG' ------
+------ LAST LOGCAT (logcat -L -b all -v threadtime -v printable -v uid -d *:v) ------
+12-02 24:01:13.237  1000   666   667 I flutter : last
+";
+
+    #[test]
+    fn should_read_log_sections() {
+        let mut lines = Vec::new();
+        read_text(BUGREPORT.as_bytes(), |section, line| lines.push((section, line.to_owned()))).expect("To read");
+
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[0], (Section::System, "--------- beginning of main\n".to_owned()));
+        assert_eq!(lines[1].0, Section::System);
+        assert!(lines[1].1.ends_with("system\n"));
+        assert_eq!(lines[2].0, Section::Event);
+        assert!(lines[2].1.ends_with("event\n"));
+        assert_eq!(lines[3].0, Section::Radio);
+        assert!(lines[3].1.ends_with("radio\n"));
+        assert_eq!(lines[4].0, Section::LastLogcat);
+        assert!(lines[4].1.ends_with("last\n"));
+    }
+
+    #[test]
+    fn should_read_zip_main_entry() {
+        use std::io::Write;
+
+        let mut archive = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        let options = zip::write::FileOptions::default();
+        archive.start_file("main_entry.txt", options).expect("To start file");
+        archive.write_all(b"bugreport-device-2021-12-02.txt").expect("To write");
+        archive.start_file("bugreport-device-2021-12-02.txt", options).expect("To start file");
+        archive.write_all(BUGREPORT.as_bytes()).expect("To write");
+        let archive = archive.finish().expect("To finish zip");
+
+        let mut count = 0;
+        read_zip(archive, |_, _| count += 1).expect("To read");
+        assert_eq!(count, 5);
+    }
+}
@@ -6,26 +6,27 @@ const TIME_PARSE: &'static [time::format_description::FormatItem<'static>] = tim
 const DATETIME_PARSE: &'static [time::format_description::FormatItem<'static>] = time::macros::format_description!("%Y-%m-%d %H:%M:%S");
 
 const FULL_DATETIME_FMT: &'static [time::format_description::FormatItem<'static>] = time::macros::format_description!("%Y-%m-%d %H:%M:%S.0");
+const EVENT_LOG_TAGS: &str = "/system/etc/event-log-tags";
 
 #[derive(Debug)]
-pub struct Level(char);
+pub struct Level(crate::Level);
 
 impl core::str::FromStr for Level {
     type Err = ();
 
     fn from_str(text: &str) -> Result<Self, Self::Err> {
         if text.eq_ignore_ascii_case("v") || text.eq_ignore_ascii_case("verbose") {
-            Ok(Level('V'))
+            Ok(Level(crate::Level::Verbose))
         } else if text.eq_ignore_ascii_case("d") || text.eq_ignore_ascii_case("debug") {
-            Ok(Level('D'))
+            Ok(Level(crate::Level::Debug))
         } else if text.eq_ignore_ascii_case("i") || text.eq_ignore_ascii_case("info") {
-            Ok(Level('I'))
+            Ok(Level(crate::Level::Info))
         } else if text.eq_ignore_ascii_case("w") || text.eq_ignore_ascii_case("warning") {
-            Ok(Level('W'))
+            Ok(Level(crate::Level::Warn))
         } else if text.eq_ignore_ascii_case("e") || text.eq_ignore_ascii_case("error") {
-            Ok(Level('E'))
+            Ok(Level(crate::Level::Error))
         } else if text.eq_ignore_ascii_case("f") || text.eq_ignore_ascii_case("fatal") {
-            Ok(Level('F'))
+            Ok(Level(crate::Level::Fatal))
         } else {
             Err(())
         }
@@ -35,11 +36,11 @@ impl core::str::FromStr for Level {
 impl Default for Level {
     #[inline(always)]
     fn default() -> Self {
-        Level('V')
+        Level(crate::Level::Verbose)
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum App {
     Pid(u64),
     PackageName(String),
@@ -223,18 +224,26 @@ pub struct Cli {
     ///Whether to include time. Default: false.
     pub time: bool,
 
+    #[arg(long = "show-pid")]
+    ///Whether to include PID and TID. Default: false.
+    pub show_pid: bool,
+
     #[arg(long = "tag-width")]
     ///Specifies tag width. Default: 23.
     pub tag_width: usize,
 
     #[arg(short, long)]
-    ///List of tags to include into output.
-    pub tag: Vec<String>,
+    ///List of tags to include into output. Supports globs (`Camera*`) and regexes (`/^cr_/`).
+    pub tag: Vec<crate::tag::TagPattern>,
 
     #[arg(short, long, default_value)]
     ///Specifies minimum Android log level to include. Default Verbose.
     pub level: Level,
 
+    #[arg(long)]
+    ///Logcat filter spec, e.g. `OkHttp:W Flutter:V *:I`. Overrides level.
+    pub filterspec: Vec<crate::filterspec::FilterSpec>,
+
     #[arg(short = "L", long)]
     ///Dumps logs prior to the last reboot.
     pub last: bool,
@@ -243,153 +252,262 @@ pub struct Cli {
     ///Strips output of color, making it more suitable for parsing.
     pub machine: bool,
 
+    #[arg(long)]
+    ///Groups lines of Java exceptions and native crashes, printing summary of crashes on exit.
+    pub crashes: bool,
+
+    #[arg(long = "crash-dir")]
+    ///Directory to write each native crash into, as separate file. Implies --crashes.
+    pub crash_dir: Option<String>,
+
+    #[arg(long)]
+    ///Directory with unstripped native libraries to resolve source locations of native crash frames. Implies --crashes.
+    pub symbols: Option<String>,
+
+    #[arg(long)]
+    ///Path to addr2line tool. Default: llvm-addr2line or addr2line.
+    pub addr2line: Option<String>,
+
+    #[arg(long)]
+    ///ProGuard/R8 mapping file to retrace obfuscated stack traces and class names.
+    pub mapping: Option<String>,
+
+    #[arg(long)]
+    ///Outputs JSON object per line, without color.
+    pub json: bool,
+
+    #[arg(long)]
+    ///Output template in format `{field[:[<>^]width][!color]}`, e.g. `{time} {pid:>5} {level!auto} {tag:<20!auto} {msg}`.
+    pub format: Option<String>,
+
+    #[arg(long)]
+    ///Joins consecutive lines of the same tag, level, pid and tid within window in milliseconds into single message.
+    pub merge: Option<u64>,
+
     #[arg(short, long)]
     ///Print only provided number of lines and exits.
     pub max_count: Option<core::num::NonZeroU64>,
 
     #[arg(short, long)]
-    ///Specifies device's serial number.
-    pub serial: Option<String>,
+    ///Specifies device's serial number. Can be specified multiple times to merge output of devices.
+    pub serial: Vec<String>,
+
+    #[arg(long)]
+    ///Waits for device to reconnect when it is disconnected, resuming logcat since last line.
+    pub reconnect: bool,
+
+    #[arg(long = "all-devices")]
+    ///Merges output of all connected devices.
+    pub all_devices: bool,
+
+    #[arg(short = "v", long = "logcat-format")]
+    ///Logcat output format and its modifiers. Default: threadtime.
+    pub logcat_format: Vec<String>,
+
+    #[arg(short = "B", long)]
+    ///Reads logcat in binary format, keeping multi-line messages, exact time and buffer of each line. Applies to files too.
+    pub binary: bool,
+
+    #[arg(long = "event-tags")]
+    ///event-log-tags file to decode events of binary logcat. Default: pulled from device.
+    pub event_tags: Option<String>,
 
     #[arg(short = "e", long)]
-    ///Makes regex against which to match log lines.
-    pub regex: Vec<String>,
+    ///Includes only lines with tag or message matching regex.
+    pub regex: Vec<regex::Regex>,
+
+    #[arg(short = "E", long = "exclude-regex")]
+    ///Excludes lines with tag or message matching regex.
+    pub exclude_regex: Vec<regex::Regex>,
+
+    #[arg(long)]
+    ///Boolean filter expression over tag, level, pid, tid, msg and time (e.g. `tag ~ ^Net && level >= W`).
+    pub filter: Option<String>,
+
+    #[arg(long)]
+    ///Color to highlight regex matches with (name, ANSI 256 code or `r,g,b`). Default: red.
+    pub highlight: Option<termcolor::Color>,
 
     #[arg(long = "time-limit")]
     ///Prints within time range from specified time to the current time.
     pub time_limit: Option<Time>,
 
     #[arg(short, long = "ignored-tag")]
-    ///List of tags to exclude from output.
-    pub ignored_tag: Vec<String>,
+    ///List of tags to exclude from output. Supports globs (`Camera*`) and regexes (`/^cr_/`).
+    pub ignored_tag: Vec<crate::tag::TagPattern>,
+
+    #[arg(short, long)]
+    ///Reads logcat output from file instead of adb. Use `-` to read stdin.
+    pub file: Vec<String>,
+
+    #[arg(long)]
+    ///Reads logcat sections of bugreport zip or its main text file instead of adb.
+    pub bugreport: Option<String>,
+
+    #[arg(long)]
+    ///Bugreport section to include: system, event, radio or last. Default: all.
+    pub section: Vec<crate::bugreport::Section>,
+
+    #[arg(long = "sub-processes")]
+    ///Includes sub-processes of app (e.g. com.foo:service), when filtering by package name.
+    pub sub_processes: bool,
 
     ///Package name or pid by which to filter logcat. If multiple apps found with the same name, it will output for every match
     pub app: Option<App>,
 }
 
 impl Cli {
-    pub fn set_current_app(&mut self) -> Result<(), isize> {
-        use crate::errors::{DUMPSYS_FAIL, ADB_FAIL, UTF8_ERROR};
-
-        let adb = std::process::Command::new("adb").arg("shell").arg("dumpsys").arg("activity").arg("activities").output();
-        let output = match adb {
-            Ok(output) => match output.status.success() {
-                true => output,
-                false => {
-                    eprintln!("Unable to find current app in dympsys");
-                    return Err(DUMPSYS_FAIL);
-                },
-            },
-            Err(error) => {
-                eprintln!("Failed to lookup current app: {}", error);
-                return Err(ADB_FAIL);
-            }
-        };
-        let mut output = match core::str::from_utf8(&output.stdout) {
-            Ok(output) => output,
+    fn get_dumpsys(&self, serial: Option<&str>, service: &str, args: &[&str]) -> Result<String, isize> {
+        use crate::errors::{DUMPSYS_FAIL, UTF8_ERROR};
+
+        let mut command = vec!["dumpsys", service];
+        command.extend_from_slice(args);
+        let output = self.get_shell_output(serial, &command)?;
+        if !output.is_success() {
+            eprintln!("Unable to find current app in dympsys");
+            return Err(DUMPSYS_FAIL);
+        }
+
+        match String::from_utf8(output.stdout) {
+            Ok(output) => Ok(output),
             Err(_) => {
                 eprintln!("stdout output is not UTF-8");
-                return Err(UTF8_ERROR);
+                Err(UTF8_ERROR)
             }
-        };
-
-        const TASK_RECORD: &str = "TaskRecord";
-        const APP_PREFIX: &str = " A=";
-        let app = loop {
-            if let Some(record_idx) = output.find(TASK_RECORD) {
-                if let Some(output) = output.get(record_idx + TASK_RECORD.len()..) {
-                    if let Some(app_name_idx) = output.find(APP_PREFIX) {
-                        if let Some(output) = output.get(app_name_idx + APP_PREFIX.len()..) {
-                            if let Some(limit) = output.find(' ') {
-                                break Some(App::PackageName(output[..limit].to_owned()));
-                            } else {
-                                break Some(App::PackageName(output.to_owned()));
-                            }
-                        }
-                    }
-                }
+        }
+    }
 
-                output = &output[record_idx..];
-            } else {
-                break None;
-            }
-        };
+    ///Returns app, which is currently running on device, if any.
+    pub fn get_current_app(&self, serial: Option<&str>) -> Result<Option<App>, isize> {
+        let output = self.get_dumpsys(serial, "activity", &["activities"])?;
+        let mut app = crate::dumpsys::resumed_package(&output).map(|package| App::PackageName(package.to_owned()));
 
         if app.is_none() {
-            println!(">No app currently running");
+            let output = self.get_dumpsys(serial, "window", &[])?;
+            app = crate::dumpsys::focused_package(&output).map(|package| App::PackageName(package.to_owned()));
         }
-        self.app = app;
 
-        Ok(())
+        Ok(app)
     }
 
-    pub fn get_app_pid(&self) -> Result<Vec<u64>, isize> {
+    pub fn get_app_pid(&self, serial: Option<&str>, app: Option<&App>) -> Result<Vec<u64>, isize> {
         const CANNOT_FIND: &str = "Cannot find application by provided name";
-        const PS_SPACE: &[char] = &[' ', '\t'];
 
-        let name = match self.app.as_ref() {
+        let name = match app {
             Some(App::Pid(pid)) => return Ok(vec![*pid]),
             None => return Ok(Vec::new()),
             Some(App::PackageName(name)) => name.as_str(),
         };
 
-        let mut cmd = self.get_adb_cmd();
+        let result = self.find_app_pids(serial, name)?;
+        if result.is_empty() {
+            eprintln!("{}", CANNOT_FIND);
+            Err(1)
+        } else {
+            Ok(result)
+        }
+    }
 
-        cmd.arg("shell").arg("ps");
+    ///Returns adb server transport to reach device, preferring serial over `-d` and `-e` as adb does.
+    pub fn get_transport(&self, serial: Option<&str>) -> crate::adb::Transport {
+        use crate::adb::Transport;
 
-        let output = match cmd.output() {
-            Ok(output) => output,
-            Err(_) => {
-                eprintln!("{}", CANNOT_FIND);
-                return Err(crate::errors::ADB_FAIL);
+        if let Some(serial) = serial {
+            Transport::Serial(serial.to_owned())
+        } else if self.device {
+            Transport::Usb
+        } else if self.emulator {
+            Transport::Local
+        } else {
+            Transport::Any
+        }
+    }
+
+    //Talks to adb server directly, falling back to adb binary (e.g. when server is not running yet).
+    fn get_shell_output(&self, serial: Option<&str>, args: &[&str]) -> Result<crate::adb::ShellOutput, isize> {
+        let command = args.iter().map(|arg| crate::adb::quote(arg)).collect::<Vec<_>>().join(" ");
+        if let Ok(output) = crate::adb::Client::default().shell(&self.get_transport(serial), &command) {
+            return Ok(output);
+        }
+
+        match self.get_adb_cmd(serial).arg("shell").args(args).output() {
+            Ok(output) => Ok(output.into()),
+            Err(error) => {
+                eprintln!("Failed to run adb shell {}: {}", args[0], error);
+                Err(crate::errors::ADB_FAIL)
             }
-        };
+        }
+    }
 
-        if output.status.success() {
-            if let Ok(stdout) = core::str::from_utf8(&output.stdout) {
-                let mut result = Vec::new();
-
-                for line in stdout.lines() {
-                    if line.contains(name) {
-                        let mut parts = line.split(PS_SPACE);
-                        //Skip user
-                        parts.next();
-                        let mut pid = None;
-                        for part in parts {
-                            let part = part.trim();
-                            if !part.is_empty() {
-                                pid = Some(part);
-                                break;
-                            }
-                        }
-
-                        if let Some(pid) = pid {
-                            match pid.parse() {
-                                Ok(pid) => {
-                                    result.push(pid);
-                                },
-                                Err(error) => {
-                                    eprintln!("Cannot parse pid='{pid}': {error}");
-                                }
-                            }
-                        }
-                    }
+    ///Returns pids of application's processes, which is empty if application is not running.
+    pub fn find_app_pids(&self, serial: Option<&str>, name: &str) -> Result<Vec<u64>, isize> {
+        use crate::process::{parse_pidof, parse_ps, is_app_process};
+
+        //pidof matches exact name only, so it cannot find sub-processes.
+        if !self.sub_processes {
+            let output = self.get_shell_output(serial, &["pidof", name])?;
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            //pidof fails without output if there is no process.
+            if output.is_success() || (output.status == Some(1) && stdout.trim().is_empty() && output.stderr.is_empty()) {
+                if let Some(pids) = parse_pidof(&stdout) {
+                    return Ok(pids.into_iter().map(u64::from).collect());
                 }
+            }
+        }
 
-                if !result.is_empty() {
-                    return Ok(result)
-                }
+        let output = self.get_shell_output(serial, &["ps", "-A", "-o", "PID,NAME"])?;
+        let mut processes = match output.is_success() {
+            true => parse_ps(&String::from_utf8_lossy(&output.stdout)),
+            false => Vec::new(),
+        };
+
+        //Old devices do not support options and list all processes by default.
+        if processes.is_empty() {
+            let output = self.get_shell_output(serial, &["ps"])?;
+            if output.is_success() {
+                processes = parse_ps(&String::from_utf8_lossy(&output.stdout));
             }
         }
 
-        eprintln!("{}", CANNOT_FIND);
-        Err(1)
+        Ok(processes.into_iter().filter(|process| is_app_process(&process.name, name, self.sub_processes)).map(|process| u64::from(process.pid)).collect())
+    }
+
+    ///Returns serials of connected devices.
+    pub fn get_devices(&self) -> Result<Vec<String>, isize> {
+        let output = match crate::adb::Client::default().devices() {
+            Ok(output) => output,
+            Err(_) => match std::process::Command::new("adb").arg("devices").output() {
+                Ok(output) => String::from_utf8_lossy(&output.stdout).into_owned(),
+                Err(error) => {
+                    eprintln!("Failed to run adb devices: {}", error);
+                    return Err(crate::errors::ADB_FAIL);
+                }
+            },
+        };
+
+        let devices = crate::device::parse_devices(&output);
+        if devices.is_empty() {
+            eprintln!("No devices connected");
+            return Err(crate::errors::ADB_FAIL);
+        }
+        Ok(devices)
+    }
+
+    ///Returns serials of devices to read logcat from, where none means default device.
+    pub fn get_serials(&self) -> Result<Vec<Option<String>>, isize> {
+        if self.all_devices {
+            self.get_devices().map(|devices| devices.into_iter().map(Some).collect())
+        } else if self.serial.is_empty() {
+            Ok(vec![None])
+        } else {
+            Ok(self.serial.iter().cloned().map(Some).collect())
+        }
     }
 
-    pub fn get_adb_cmd(&self) -> std::process::Command {
+    pub fn get_adb_cmd(&self, serial: Option<&str>) -> std::process::Command {
         let mut adb = std::process::Command::new("adb");
 
-        if let Some(serial) = self.serial.as_ref() {
+        if let Some(serial) = serial {
             adb.arg("-s");
             adb.arg(serial);
         }
@@ -405,52 +523,121 @@ impl Cli {
         adb
     }
 
-    pub fn get_logcat_cmd(&self) -> std::process::Command {
-        let mut adb = self.get_adb_cmd();
+    #[inline]
+    pub fn get_logcat_cmd(&self, serial: Option<&str>) -> std::process::Command {
+        let mut adb = self.get_adb_cmd(serial);
         adb.arg("logcat");
+        adb.args(self.get_logcat_args());
+        adb
+    }
+
+    #[inline]
+    ///Returns arguments of logcat, i.e. without `logcat` itself.
+    pub fn get_logcat_args(&self) -> Vec<String> {
+        let time_limit = self.time_limit.as_ref().map(|time_limit| time_limit.0.format(FULL_DATETIME_FMT).expect("To format time"));
+        self.get_logcat_args_since(time_limit.as_deref())
+    }
+
+    ///Returns arguments of logcat that prints lines since `time`, if specified, instead of `--time-limit`.
+    pub fn get_logcat_args_since(&self, time: Option<&str>) -> Vec<String> {
+        let mut args = Vec::new();
 
         for buffer in self.buffer.iter() {
-            adb.arg("-b");
-            adb.arg(&buffer);
+            args.push("-b".to_owned());
+            args.push(buffer.clone());
         }
 
-        for regex in self.regex.iter() {
-            adb.arg("-e");
-            adb.arg(&regex);
+        if self.binary {
+            args.push("-B".to_owned());
+        } else if self.logcat_format.is_empty() {
+            args.push("-v".to_owned());
+            args.push("threadtime".to_owned());
+        }
+
+        for format in self.logcat_format.iter().filter(|_| !self.binary) {
+            args.push("-v".to_owned());
+            args.push(format.clone());
         }
 
         if let Some(max_count) = self.max_count {
-            adb.arg("-m");
-            adb.arg(&format!("{}", max_count));
+            args.push("-m".to_owned());
+            args.push(format!("{}", max_count));
         }
 
         if self.dump {
-            adb.arg("-d");
+            args.push("-d".to_owned());
         }
 
         if self.last {
-            adb.arg("-L");
+            args.push("-L".to_owned());
         }
 
-        if let Some(ref time_limit) = self.time_limit {
-            adb.arg("-T");
-            adb.arg(&time_limit.0.format(FULL_DATETIME_FMT).expect("To format time"));
+        if let Some(time) = time {
+            args.push("-T".to_owned());
+            args.push(time.to_owned());
         }
 
-        adb
+        args
     }
 
-    pub fn get_filter_spec(&self) -> Option<String> {
-        if self.level.0 == 'V' {
-            None
-        } else {
-            Some(format!("*:{}", self.level.0))
+    ///Clears logcat buffers of device.
+    pub fn clear_logcat(&self, serial: Option<&str>) -> Result<(), isize> {
+        let mut args = vec!["logcat"];
+        for buffer in self.buffer.iter() {
+            args.push("-b");
+            args.push(buffer);
+        }
+        args.push("-c");
+
+        let output = self.get_shell_output(serial, &args)?;
+        match output.is_success() {
+            true => Ok(()),
+            false => {
+                eprintln!("Failed to clear logcat: {}", String::from_utf8_lossy(&output.stderr).trim());
+                Err(crate::errors::ADB_FAIL)
+            }
+        }
+    }
+
+    #[inline]
+    ///Returns whether binary logcat includes buffers with events.
+    pub fn is_events(&self) -> bool {
+        self.binary && self.buffer.iter().flat_map(|buffer| buffer.split(',')).any(|buffer| match buffer {
+            "events" | "stats" | "security" | "all" => true,
+            _ => false,
+        })
+    }
+
+    ///Returns event tags of device, if they can be read.
+    pub fn get_event_tags(&self, serial: Option<&str>) -> Option<crate::events::EventTags> {
+        let output = self.get_shell_output(serial, &["cat", EVENT_LOG_TAGS]).ok()?;
+        let tags = crate::events::EventTags::parse(&String::from_utf8_lossy(&output.stdout));
+        match output.is_success() && !tags.is_empty() {
+            true => Some(tags),
+            false => {
+                eprintln!("Unable to read {}", EVENT_LOG_TAGS);
+                None
+            }
+        }
+    }
+
+    #[inline]
+    ///Returns whether crashes should be grouped, as requested by any of crash options.
+    pub fn is_crashes(&self) -> bool {
+        self.crashes || self.crash_dir.is_some() || self.symbols.is_some()
+    }
+
+    pub fn get_filter_spec(&self) -> crate::filterspec::FilterSpec {
+        let mut result = crate::filterspec::FilterSpec::new(self.level.0);
+        for spec in self.filterspec.iter() {
+            result.extend(spec);
         }
+        result
     }
 }
 
 pub fn new<'a, T: IntoIterator<Item = &'a str>>(args: T) -> Result<Cli, isize> {
-    match Cli::from_args(args.into_iter()) {
+    match Cli::from_args(args) {
         Ok(args) => Ok(args),
         Err(error) if error.is_help() => {
             println!("{}", error);
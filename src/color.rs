@@ -38,6 +38,12 @@ impl Stack {
     }
 }
 
+///Returns color of device label by its index.
+pub fn device_color(idx: usize) -> termcolor::Color {
+    const COLORS: [termcolor::Color; 4] = [termcolor::Color::Cyan, termcolor::Color::Magenta, termcolor::Color::Yellow, termcolor::Color::Green];
+    COLORS[idx % COLORS.len()]
+}
+
 #[cfg(test)]
 mod tests {
     use super::Stack;
@@ -0,0 +1,623 @@
+use crate::LogCatLine;
+use crate::parser::OwnedLine;
+
+use std::collections::VecDeque;
+
+//Number of lines from other processes, after which pending trace is considered complete.
+const MAX_FOREIGN_LINES: usize = 16;
+const FATAL_EXCEPTION: &str = "FATAL EXCEPTION";
+const PROCESS_PREFIX: &str = "Process: ";
+const CAUSED_BY_PREFIX: &str = "Caused by: ";
+const EXCEPTION_REGEX: &str = r"^(?:[A-Za-z_$][\w$]*\.)+[A-Za-z_$][\w$]*(?:Exception|Error|Throwable)(?::.*)?$";
+
+#[derive(Debug, Clone, Default)]
+///Summary of Java/Kotlin exception.
+pub struct JavaCrash {
+    ///Whether exception crashed process (i.e. `FATAL EXCEPTION`).
+    pub fatal: bool,
+    pub date: String,
+    pub time: String,
+    ///Process name, if known.
+    pub process: String,
+    pub pid: Option<u32>,
+    ///Exception line, e.g. `java.lang.NullPointerException: message`.
+    pub exception: String,
+    ///Last `Caused by:` exception, if any.
+    pub cause: String,
+}
+
+///Lines of Java/Kotlin exception.
+pub(crate) struct JavaTrace {
+    pub crash: JavaCrash,
+    ///Number of stack frames.
+    pub frames: usize,
+    pub lines: Vec<OwnedLine>,
+}
+
+impl JavaTrace {
+    #[inline]
+    ///Returns whether lines constitute stack trace, rather than lonely exception line.
+    pub fn is_trace(&self) -> bool {
+        self.crash.fatal || self.frames > 0
+    }
+}
+
+struct Pending {
+    trace: JavaTrace,
+    tag: String,
+    foreign: usize,
+}
+
+#[inline]
+fn is_frame(msg: &str) -> bool {
+    msg.starts_with("at ") || (msg.starts_with("... ") && msg.ends_with(" more"))
+}
+
+#[inline]
+fn is_continuation(msg: &str) -> bool {
+    is_frame(msg) || msg.starts_with(CAUSED_BY_PREFIX) || msg.starts_with("Suppressed: ")
+}
+
+///Groups lines of Java/Kotlin exceptions by process and tag.
+pub(crate) struct JavaDetector {
+    exception: regex::Regex,
+    pending: Vec<Pending>,
+    ready: VecDeque<JavaTrace>,
+}
+
+impl JavaDetector {
+    pub fn new() -> Self {
+        Self {
+            exception: regex::Regex::new(EXCEPTION_REGEX).expect("valid exception regex"),
+            pending: Vec::new(),
+            ready: VecDeque::new(),
+        }
+    }
+
+    fn add(trace: &mut JavaTrace, line: &LogCatLine<'_>) {
+        let msg = line.msg.trim();
+        if is_frame(msg) {
+            trace.frames += 1;
+        } else if let Some(cause) = msg.strip_prefix(CAUSED_BY_PREFIX) {
+            trace.crash.cause = cause.to_owned();
+        } else if let Some(process) = msg.strip_prefix(PROCESS_PREFIX) {
+            if trace.crash.process.is_empty() {
+                trace.crash.process = process.split(',').next().unwrap_or(process).to_owned();
+            }
+        } else if trace.crash.exception.is_empty() && !msg.starts_with(FATAL_EXCEPTION) {
+            trace.crash.exception = msg.to_owned();
+        }
+
+        trace.lines.push(OwnedLine::new(line));
+    }
+
+    fn complete(&mut self, idx: usize) {
+        let pending = self.pending.remove(idx);
+        self.ready.push_back(pending.trace);
+    }
+
+    ///Handles line, returning whether it is buffered as part of exception.
+    ///
+    ///Completed exceptions become available via `pop_ready`
+    pub fn push(&mut self, line: &LogCatLine<'_>) -> bool {
+        let msg = line.msg.trim();
+        let is_fatal = msg.starts_with(FATAL_EXCEPTION);
+
+        if let Some(idx) = self.pending.iter().position(|pending| pending.trace.crash.pid == line.pid && pending.tag == line.tag) {
+            let pending = &mut self.pending[idx];
+            //Fatal exception includes everything logged by `AndroidRuntime` of the process
+            if !is_fatal && (pending.trace.crash.fatal || is_continuation(msg)) {
+                Self::add(&mut pending.trace, line);
+                pending.foreign = 0;
+                return true;
+            }
+
+            self.complete(idx);
+        }
+
+        let mut idx = 0;
+        while idx < self.pending.len() {
+            //Lonely exception line is not held back behind lines of other processes, unless stack trace follows it.
+            let max_foreign = match self.pending[idx].trace.is_trace() {
+                true => MAX_FOREIGN_LINES,
+                false => 0,
+            };
+            self.pending[idx].foreign += 1;
+            if self.pending[idx].foreign > max_foreign {
+                self.complete(idx);
+            } else {
+                idx += 1;
+            }
+        }
+
+        if is_fatal || self.exception.is_match(msg) {
+            let mut trace = JavaTrace {
+                crash: JavaCrash {
+                    fatal: is_fatal,
+                    date: line.date.to_owned(),
+                    time: line.time.to_owned(),
+                    pid: line.pid,
+                    ..Default::default()
+                },
+                frames: 0,
+                lines: Vec::new(),
+            };
+            Self::add(&mut trace, line);
+
+            self.pending.push(Pending {
+                trace,
+                tag: line.tag.to_owned(),
+                foreign: 0,
+            });
+            true
+        } else {
+            false
+        }
+    }
+
+    #[inline]
+    pub fn pop_ready(&mut self) -> Option<JavaTrace> {
+        self.ready.pop_front()
+    }
+
+    ///Completes all pending exceptions.
+    pub fn finish(&mut self) {
+        while !self.pending.is_empty() {
+            self.complete(0);
+        }
+    }
+}
+
+const DEBUG_TAG: &str = "DEBUG";
+const LIBC_TAG: &str = "libc";
+const NATIVE_HEADER: &str = "*** *** *** *** *** *** *** *** *** *** *** *** *** *** *** ***";
+const FATAL_SIGNAL: &str = "Fatal signal ";
+const ABORT_PREFIX: &str = "Abort message: ";
+const BACKTRACE: &str = "backtrace:";
+const TOMBSTONE_WRITTEN: &str = "Tombstone written to: ";
+const SIGNAL_REGEX: &str = r"signal (\d+) \((\w+)\), code (-?\d+) \(([^)]*)\)(?:, fault addr (\S+))?";
+//`libc: Fatal signal ... in tid 1234 (RenderThread), pid 1200 (com.foo)`
+const LIBC_PROCESS_REGEX: &str = r" in tid (\d+) \(([^)]*)\), pid (\d+) \(([^)]*)\)";
+//`DEBUG: pid: 1200, tid: 1234, name: RenderThread  >>> com.foo <<<`
+const DEBUG_PROCESS_REGEX: &str = r"^pid: (\d+), tid: (\d+), name: (.*?)\s+>>> (.*?) <<<";
+//`DEBUG: #00 pc 000000000001a2b4  /data/app/lib/arm64/libfoo.so (foo()+12) (BuildId: abcdef)`
+const FRAME_REGEX: &str = r"^#(\d+) pc ([0-9a-fA-F]+)\s+(\S+)(?: \(offset 0x[0-9a-fA-F]+\))?(?: \((.+?)\))?(?: \(BuildId: ([0-9a-fA-F]+)\))?$";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+///Frame of native backtrace.
+pub struct NativeFrame {
+    pub index: u32,
+    ///Program counter, relative to library.
+    pub pc: u64,
+    ///Path to library (or binary).
+    pub library: String,
+    ///Symbol with offset, if known, e.g. `abort+164`.
+    pub symbol: String,
+    ///Empty if not present.
+    pub build_id: String,
+}
+
+#[derive(Debug, Clone, Default)]
+///Summary of native crash (i.e. tombstone dumped by `debuggerd`).
+pub struct NativeCrash {
+    pub date: String,
+    pub time: String,
+    ///Process name, if known.
+    pub process: String,
+    ///Name of crashed thread, if known.
+    pub thread: String,
+    pub pid: Option<u32>,
+    pub tid: Option<u32>,
+    ///Signal name, e.g. `SIGSEGV`.
+    pub signal: String,
+    ///Signal code name, e.g. `SEGV_MAPERR`.
+    pub code: String,
+    ///Empty if not present.
+    pub fault_addr: String,
+    ///Empty if not present.
+    pub abort: String,
+    ///Backtrace of crashed thread.
+    pub frames: Vec<NativeFrame>,
+}
+
+impl NativeCrash {
+    ///Returns description of signal, e.g. `SIGSEGV (SEGV_MAPERR), fault addr 0x0`.
+    pub fn signal_text(&self) -> String {
+        let mut result = self.signal.clone();
+        if !self.code.is_empty() {
+            result.push_str(" (");
+            result.push_str(&self.code);
+            result.push(')');
+        }
+        if !self.fault_addr.is_empty() && self.fault_addr != "--------" {
+            result.push_str(", fault addr ");
+            result.push_str(&self.fault_addr);
+        }
+        result
+    }
+}
+
+#[derive(Debug, Clone)]
+///Crash summary.
+pub enum Crash {
+    Java(JavaCrash),
+    Native(NativeCrash),
+}
+
+///Lines of native crash.
+pub(crate) struct NativeTrace {
+    pub crash: NativeCrash,
+    pub lines: Vec<OwnedLine>,
+    //Index of line for each frame of backtrace.
+    frame_lines: Vec<usize>,
+}
+
+impl NativeTrace {
+    ///Inserts source location of frames under their lines.
+    pub fn symbolize(&mut self, symbolizer: &mut crate::symbol::Symbolizer) {
+        let lines = core::mem::take(&mut self.lines);
+        let mut frame_lines = self.frame_lines.iter().zip(self.crash.frames.iter()).peekable();
+
+        for (idx, line) in lines.into_iter().enumerate() {
+            let frame = match frame_lines.peek() {
+                Some((frame_line, frame)) if **frame_line == idx => Some(*frame),
+                _ => None,
+            };
+            self.lines.push(line);
+
+            let frame = match frame {
+                Some(frame) => {
+                    frame_lines.next();
+                    frame
+                },
+                None => continue,
+            };
+
+            let indent = {
+                let msg = self.lines[self.lines.len() - 1].as_line().msg;
+                msg.len() - msg.trim_start().len()
+            };
+            for location in symbolizer.resolve(frame) {
+                let msg = match location.file.is_empty() {
+                    true => format!("{:indent$}    {}", "", location.function, indent=indent),
+                    false => format!("{:indent$}    {} at {}:{}", "", location.function, location.file, location.line, indent=indent),
+                };
+                let line = self.lines[self.lines.len() - 1].as_line();
+                let line = OwnedLine::new(&LogCatLine {
+                    msg: &msg,
+                    ..line
+                });
+                self.lines.push(line);
+            }
+        }
+
+        //Lines no longer correspond to frames.
+        self.frame_lines.clear();
+    }
+
+    ///Returns name of file to store crash in.
+    pub fn file_name(&self) -> String {
+        let name = format!("native_crash_{}_{}_{}.txt", self.crash.date, self.crash.time, self.crash.pid.unwrap_or(0));
+        name.chars().map(|ch| match ch.is_ascii_alphanumeric() || ch == '_' || ch == '.' {
+            true => ch,
+            false => '-',
+        }).collect()
+    }
+
+    ///Writes messages of crash as plain text, similar to tombstone.
+    pub fn write_text<W: std::io::Write>(&self, out: &mut W) -> std::io::Result<()> {
+        for line in self.lines.iter() {
+            writeln!(out, "{}", line.as_line().msg)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Backtrace {
+    Pending,
+    Active,
+    Done,
+}
+
+struct PendingNative {
+    trace: NativeTrace,
+    //Whether `DEBUG` header is already received.
+    header: bool,
+    backtrace: Backtrace,
+    foreign: usize,
+}
+
+struct NativePatterns {
+    signal: regex::Regex,
+    libc_process: regex::Regex,
+    debug_process: regex::Regex,
+    frame: regex::Regex,
+}
+
+impl NativePatterns {
+    fn new() -> Self {
+        Self {
+            signal: regex::Regex::new(SIGNAL_REGEX).expect("valid signal regex"),
+            libc_process: regex::Regex::new(LIBC_PROCESS_REGEX).expect("valid libc process regex"),
+            debug_process: regex::Regex::new(DEBUG_PROCESS_REGEX).expect("valid debug process regex"),
+            frame: regex::Regex::new(FRAME_REGEX).expect("valid frame regex"),
+        }
+    }
+
+    fn add(&self, pending: &mut PendingNative, line: &LogCatLine<'_>) {
+        let crash = &mut pending.trace.crash;
+        let msg = line.msg.trim();
+
+        if pending.backtrace == Backtrace::Active {
+            match self.frame.captures(msg) {
+                Some(captures) => {
+                    pending.trace.frame_lines.push(pending.trace.lines.len());
+                    crash.frames.push(NativeFrame {
+                        index: captures[1].parse().unwrap_or(0),
+                        pc: u64::from_str_radix(&captures[2], 16).unwrap_or(0),
+                        library: captures[3].to_owned(),
+                        symbol: captures.get(4).map(|symbol| symbol.as_str().to_owned()).unwrap_or_default(),
+                        build_id: captures.get(5).map(|id| id.as_str().to_owned()).unwrap_or_default(),
+                    });
+                },
+                None => pending.backtrace = Backtrace::Done,
+            }
+        } else if msg.starts_with(NATIVE_HEADER) {
+            pending.header = true;
+        } else if msg == BACKTRACE {
+            if pending.backtrace == Backtrace::Pending {
+                pending.backtrace = Backtrace::Active;
+            }
+        } else if let Some(abort) = msg.strip_prefix(ABORT_PREFIX) {
+            crash.abort = abort.trim_matches('\'').to_owned();
+        } else if let Some(captures) = self.debug_process.captures(msg) {
+            crash.pid = captures[1].parse().ok();
+            crash.tid = captures[2].parse().ok();
+            crash.thread = captures[3].to_owned();
+            crash.process = captures[4].to_owned();
+        } else if let Some(captures) = self.signal.captures(msg) {
+            crash.signal = captures[2].to_owned();
+            crash.code = captures[4].to_owned();
+            crash.fault_addr = captures.get(5).map(|addr| addr.as_str().to_owned()).unwrap_or_default();
+
+            if let Some(captures) = self.libc_process.captures(msg) {
+                crash.tid = captures[1].parse().ok();
+                crash.thread = captures[2].to_owned();
+                crash.pid = captures[3].parse().ok();
+                crash.process = captures[4].to_owned();
+            }
+        }
+
+        pending.trace.lines.push(OwnedLine::new(line));
+    }
+}
+
+///Groups lines of native crashes, logged by `libc` and `DEBUG` tags.
+pub(crate) struct NativeDetector {
+    patterns: NativePatterns,
+    pending: Option<PendingNative>,
+    ready: VecDeque<NativeTrace>,
+}
+
+impl NativeDetector {
+    pub fn new() -> Self {
+        Self {
+            patterns: NativePatterns::new(),
+            pending: None,
+            ready: VecDeque::new(),
+        }
+    }
+
+    fn complete(&mut self) {
+        if let Some(pending) = self.pending.take() {
+            self.ready.push_back(pending.trace);
+        }
+    }
+
+    ///Handles line, returning whether it is buffered as part of native crash.
+    ///
+    ///Completed crashes become available via `pop_ready`
+    pub fn push(&mut self, line: &LogCatLine<'_>) -> bool {
+        let msg = line.msg.trim();
+        let is_debug = line.tag == DEBUG_TAG;
+        let is_header = is_debug && msg.starts_with(NATIVE_HEADER);
+        let is_signal = line.tag == LIBC_TAG && msg.starts_with(FATAL_SIGNAL);
+
+        let mut is_complete = false;
+        if let Some(pending) = self.pending.as_mut() {
+            //`libc` reports signal before `DEBUG` dumps it, so header continues crash unless it already has one.
+            let is_new = is_signal || (is_header && pending.header);
+            if is_debug && !is_new {
+                self.patterns.add(pending, line);
+                pending.foreign = 0;
+                if msg.starts_with(TOMBSTONE_WRITTEN) {
+                    self.complete();
+                }
+                return true;
+            }
+
+            pending.foreign += 1;
+            is_complete = is_new || pending.foreign > MAX_FOREIGN_LINES;
+        }
+
+        if is_complete {
+            self.complete();
+        }
+
+        if is_header || is_signal {
+            let mut pending = PendingNative {
+                trace: NativeTrace {
+                    crash: NativeCrash {
+                        date: line.date.to_owned(),
+                        time: line.time.to_owned(),
+                        ..Default::default()
+                    },
+                    lines: Vec::new(),
+                    frame_lines: Vec::new(),
+                },
+                header: false,
+                backtrace: Backtrace::Pending,
+                foreign: 0,
+            };
+            self.patterns.add(&mut pending, line);
+            self.pending = Some(pending);
+            true
+        } else {
+            false
+        }
+    }
+
+    #[inline]
+    pub fn pop_ready(&mut self) -> Option<NativeTrace> {
+        self.ready.pop_front()
+    }
+
+    ///Completes pending crash.
+    #[inline]
+    pub fn finish(&mut self) {
+        self.complete();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{JavaDetector, NativeDetector, NativeFrame};
+    use crate::parse;
+    use crate::symbol::{Location, Symbolizer};
+
+    const CRASH: &[&str] = &[
+        "12-02 24:01:13.237  666  666 E AndroidRuntime: FATAL EXCEPTION: main",
+        "12-02 24:01:13.237  666  666 E AndroidRuntime: Process: com.foo, PID: 666",
+        "12-02 24:01:13.237  777  777 I flutter : unrelated",
+        "12-02 24:01:13.237  666  666 E AndroidRuntime: java.lang.RuntimeException: Unable to start activity",
+        "12-02 24:01:13.237  666  666 E AndroidRuntime: \tat android.app.ActivityThread.main(ActivityThread.java:7356)",
+        "12-02 24:01:13.237  666  666 E AndroidRuntime: Caused by: java.lang.NullPointerException: boom",
+        "12-02 24:01:13.237  666  666 E AndroidRuntime: \t... 11 more",
+    ];
+
+    #[test]
+    fn should_group_fatal_exception() {
+        let mut detector = JavaDetector::new();
+        let consumed = CRASH.iter().map(|line| detector.push(&parse(line).expect("To parse"))).collect::<Vec<_>>();
+        assert_eq!(consumed, [true, true, false, true, true, true, true]);
+        assert!(detector.pop_ready().is_none());
+
+        detector.finish();
+        let trace = detector.pop_ready().expect("To have trace");
+        assert!(trace.is_trace());
+        assert_eq!(trace.lines.len(), 6);
+        assert_eq!(trace.frames, 2);
+        assert!(trace.crash.fatal);
+        assert_eq!(trace.crash.pid, Some(666));
+        assert_eq!(trace.crash.process, "com.foo");
+        assert_eq!(trace.crash.exception, "java.lang.RuntimeException: Unable to start activity");
+        assert_eq!(trace.crash.cause, "java.lang.NullPointerException: boom");
+    }
+
+    #[test]
+    fn should_group_non_fatal_exception() {
+        let mut detector = JavaDetector::new();
+        assert!(detector.push(&parse("12-02 24:01:13.237  666  667 W System.err: java.io.IOException: closed").expect("To parse")));
+        assert!(detector.push(&parse("12-02 24:01:13.237  666  667 W System.err: \tat com.foo.Bar.baz(Bar.kt:12)").expect("To parse")));
+        assert!(!detector.push(&parse("12-02 24:01:13.237  666  667 W System.err: done").expect("To parse")));
+
+        let trace = detector.pop_ready().expect("To have trace");
+        assert!(!trace.crash.fatal);
+        assert!(trace.is_trace());
+        assert_eq!(trace.lines.len(), 2);
+        assert_eq!(trace.crash.exception, "java.io.IOException: closed");
+
+        assert!(detector.push(&parse("12-02 24:01:13.237  666  667 W System.err: java.io.IOException: closed").expect("To parse")));
+        assert!(!detector.push(&parse("12-02 24:01:13.237  666  667 W System.err: done").expect("To parse")));
+        let trace = detector.pop_ready().expect("To have lonely exception");
+        assert!(!trace.is_trace());
+
+        assert!(detector.push(&parse("12-02 24:01:13.237  666  667 W System.err: java.io.IOException: closed").expect("To parse")));
+        assert!(!detector.push(&parse("12-02 24:01:13.237  777  777 I flutter : unrelated").expect("To parse")));
+        let trace = detector.pop_ready().expect("To complete lonely exception on foreign line");
+        assert_eq!(trace.lines.len(), 1);
+    }
+
+    const NATIVE: &[&str] = &[
+        "12-02 24:01:13.237  1200  1234 F libc    : Fatal signal 6 (SIGABRT), code -1 (SI_QUEUE) in tid 1234 (RenderThread), pid 1200 (com.foo)",
+        "12-02 24:01:13.300  1300  1300 F DEBUG   : *** *** *** *** *** *** *** *** *** *** *** *** *** *** *** ***",
+        "12-02 24:01:13.300  1300  1300 F DEBUG   : Build fingerprint: 'google/foo/foo:11/RQ1A/1:user/release-keys'",
+        "12-02 24:01:13.300  1300  1300 F DEBUG   : pid: 1200, tid: 1234, name: RenderThread  >>> com.foo <<<",
+        "12-02 24:01:13.300  777  777 I flutter : unrelated",
+        "12-02 24:01:13.300  1300  1300 F DEBUG   : signal 6 (SIGABRT), code -1 (SI_QUEUE), fault addr --------",
+        "12-02 24:01:13.300  1300  1300 F DEBUG   : Abort message: 'bad state'",
+        "12-02 24:01:13.300  1300  1300 F DEBUG   : backtrace:",
+        "12-02 24:01:13.300  1300  1300 F DEBUG   :       #00 pc 000000000004e8ec  /apex/com.android.runtime/lib64/bionic/libc.so (abort+164) (BuildId: 5812256023147338b8a2a7b5a1ea2f1e)",
+        "12-02 24:01:13.300  1300  1300 F DEBUG   :       #01 pc 0000000000012a30  /data/app/com.foo/lib/arm64/libfoo.so (offset 0x1000)",
+        "12-02 24:01:13.300  1300  1300 F DEBUG   : Tombstone written to: /data/tombstones/tombstone_00",
+    ];
+
+    #[test]
+    fn should_group_native_crash() {
+        let mut detector = NativeDetector::new();
+        let consumed = NATIVE.iter().map(|line| detector.push(&parse(line).expect("To parse"))).collect::<Vec<_>>();
+        assert_eq!(consumed, [true, true, true, true, false, true, true, true, true, true, true]);
+
+        let trace = detector.pop_ready().expect("To have crash");
+        assert_eq!(trace.lines.len(), 10);
+        assert_eq!(trace.file_name(), "native_crash_12-02_24-01-13.237_1200.txt");
+
+        let crash = trace.crash;
+        assert_eq!(crash.pid, Some(1200));
+        assert_eq!(crash.tid, Some(1234));
+        assert_eq!(crash.process, "com.foo");
+        assert_eq!(crash.thread, "RenderThread");
+        assert_eq!(crash.signal_text(), "SIGABRT (SI_QUEUE)");
+        assert_eq!(crash.abort, "bad state");
+        assert_eq!(crash.frames, [
+            NativeFrame {
+                index: 0,
+                pc: 0x4e8ec,
+                library: "/apex/com.android.runtime/lib64/bionic/libc.so".to_owned(),
+                symbol: "abort+164".to_owned(),
+                build_id: "5812256023147338b8a2a7b5a1ea2f1e".to_owned(),
+            },
+            NativeFrame {
+                index: 1,
+                pc: 0x12a30,
+                library: "/data/app/com.foo/lib/arm64/libfoo.so".to_owned(),
+                symbol: String::new(),
+                build_id: String::new(),
+            },
+        ]);
+    }
+
+    #[test]
+    fn should_symbolize_native_crash() {
+        let mut detector = NativeDetector::new();
+        for line in NATIVE {
+            detector.push(&parse(line).expect("To parse"));
+        }
+        let mut trace = detector.pop_ready().expect("To have crash");
+
+        let mut symbolizer = Symbolizer::new("/nonexistent".into(), None);
+        symbolizer.insert("/data/app/com.foo/lib/arm64/libfoo.so", 0x12a30, vec![
+            Location {
+                function: "foo::check()".to_owned(),
+                file: "foo.cpp".to_owned(),
+                line: 12,
+            },
+            Location {
+                function: "foo::run()".to_owned(),
+                file: String::new(),
+                line: 0,
+            },
+        ]);
+        trace.symbolize(&mut symbolizer);
+
+        let msgs = trace.lines.iter().skip(7).map(|line| line.as_line().msg.to_owned()).collect::<Vec<_>>();
+        assert_eq!(msgs, [
+            "      #00 pc 000000000004e8ec  /apex/com.android.runtime/lib64/bionic/libc.so (abort+164) (BuildId: 5812256023147338b8a2a7b5a1ea2f1e)",
+            "      #01 pc 0000000000012a30  /data/app/com.foo/lib/arm64/libfoo.so (offset 0x1000)",
+            "          foo::check() at foo.cpp:12",
+            "          foo::run()",
+            "Tombstone written to: /data/tombstones/tombstone_00",
+        ]);
+    }
+}
@@ -0,0 +1,24 @@
+///Parses output of `adb devices`, returning serials of devices that are online.
+///
+///Devices in other states (e.g. `offline` or `unauthorized`) are skipped.
+pub fn parse_devices(output: &str) -> Vec<String> {
+    output.lines().filter_map(|line| {
+        let mut columns = line.split_whitespace();
+        let serial = columns.next()?;
+        match columns.next() {
+            Some("device") => Some(serial.to_owned()),
+            _ => None,
+        }
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_devices;
+
+    #[test]
+    fn should_parse_devices() {
+        let output = "List of devices attached\nemulator-5554\tdevice\nR58M12ABCDE\tdevice product:a51 model:SM_A515F\n0123456789\tunauthorized\n192.168.1.2:5555\toffline\n\n";
+        assert_eq!(parse_devices(output), ["emulator-5554", "R58M12ABCDE"]);
+    }
+}
@@ -0,0 +1,153 @@
+//Markers of resumed activity in `dumpsys activity activities`, in order of preference:
+//- `mResumedActivity: ActivityRecord{..}` (Android 9 - 11);
+//- `topResumedActivity=ActivityRecord{..}` (Android 10+);
+//- `ResumedActivity: ActivityRecord{..}` (Android 12+);
+//- `mFocusedActivity: ActivityRecord{..}` (Android 7 - 8).
+const RESUMED_MARKERS: [&str; 4] = ["mResumedActivity:", "topResumedActivity=", "ResumedActivity:", "mFocusedActivity:"];
+//Legacy `TaskRecord{.. A=com.foo U=0 StackId=1 sz=1}`
+const TASK_RECORD: &str = "TaskRecord{";
+const TASK_AFFINITY: &str = " A=";
+//Markers of focus in `dumpsys window`, in order of preference.
+const FOCUS_MARKERS: [&str; 2] = ["mCurrentFocus=", "mFocusedApp="];
+
+///Returns package of component name within text, e.g. `com.foo` for `ActivityRecord{3a1b u0 com.foo/.MainActivity t12}`
+fn component_package(text: &str) -> Option<&str> {
+    text.split(|ch: char| ch.is_whitespace() || ch == '{' || ch == '}')
+        .find_map(|part| part.find('/').map(|idx| &part[..idx]))
+        .filter(|package| !package.is_empty())
+}
+
+fn find_package<'a>(output: &'a str, markers: &[&str]) -> Option<&'a str> {
+    for marker in markers {
+        for line in output.lines() {
+            let line = line.trim();
+            if let Some(record) = line.strip_prefix(marker) {
+                if let Some(package) = component_package(record) {
+                    return Some(package);
+                }
+            }
+        }
+    }
+
+    None
+}
+
+///Returns package of resumed activity from `dumpsys activity activities` output.
+pub fn resumed_package(output: &str) -> Option<&str> {
+    if let Some(package) = find_package(output, &RESUMED_MARKERS) {
+        return Some(package);
+    }
+
+    for line in output.lines() {
+        if let Some(idx) = line.find(TASK_RECORD) {
+            let record = &line[idx + TASK_RECORD.len()..];
+            if let Some(idx) = record.find(TASK_AFFINITY) {
+                let package = &record[idx + TASK_AFFINITY.len()..];
+                let package = package.split(|ch: char| ch.is_whitespace() || ch == '}').next().unwrap_or(package);
+                if !package.is_empty() {
+                    return Some(package);
+                }
+            }
+        }
+    }
+
+    None
+}
+
+///Returns package of focused window from `dumpsys window` output.
+pub fn focused_package(output: &str) -> Option<&str> {
+    find_package(output, &FOCUS_MARKERS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{resumed_package, focused_package};
+
+    const ANDROID_7: &str = "ACTIVITY MANAGER ACTIVITIES (dumpsys activity activities)
+Display #0 (activities from top to bottom):
+  Stack #1:
+    Task id #57
+    * TaskRecord{8e3c2f0 #57 A=com.foo U=0 sz=1}
+      userId=0 effectiveUid=u0a85 mCallingUid=2000 mCallingPackage=null
+        * Hist #0: ActivityRecord{3cbb9d6 u0 com.foo/.MainActivity t57}
+    Running activities (most recent first):
+      TaskRecord{8e3c2f0 #57 A=com.foo U=0 sz=1}
+        Run #0: ActivityRecord{3cbb9d6 u0 com.foo/.MainActivity t57}
+    mResumedActivity: ActivityRecord{3cbb9d6 u0 com.foo/.MainActivity t57}
+
+  mFocusedActivity: ActivityRecord{3cbb9d6 u0 com.foo/.MainActivity t57}
+  mFocusedStack=ActivityStack{5d4f2d7 stackId=1, 1 tasks} mLastFocusedStack=ActivityStack{5d4f2d7 stackId=1, 1 tasks}
+";
+
+    const ANDROID_10: &str = "ACTIVITY MANAGER ACTIVITIES (dumpsys activity activities)
+Display #0 (activities from top to bottom):
+
+  Stack #58: type=standard mode=fullscreen
+  isSleeping=false
+  mBounds=Rect(0, 0 - 0, 0)
+    Task id #58
+    mBounds=Rect(0, 0 - 0, 0)
+    mMinWidth=-1
+    * TaskRecord{6d1c7b2 #58 A=com.bar U=0 StackId=58 sz=1}
+      userId=0 effectiveUid=u0a150 mCallingUid=u0a96 mCallingPackage=com.google.android.apps.nexuslauncher
+      affinity=com.bar
+        * Hist #0: ActivityRecord{a0f8bd1 u0 com.bar/.ui.HomeActivity t58}
+    mResumedActivity: ActivityRecord{a0f8bd1 u0 com.bar/.ui.HomeActivity t58}
+    mLastPausedActivity: ActivityRecord{b1e2 u0 com.google.android.apps.nexuslauncher/.NexusLauncherActivity t2}
+
+ ResumedActivity:ActivityRecord{a0f8bd1 u0 com.bar/.ui.HomeActivity t58}
+  mFocusedStack=ActivityStack{2e5ca9f stackId=58 type=standard mode=fullscreen visible=true translucent=false, 1 tasks}
+";
+
+    const ANDROID_12: &str = "ACTIVITY MANAGER ACTIVITIES (dumpsys activity activities)
+Display #0 (activities from top to bottom):
+  * Task{ba3a3f5 #41 type=standard A=10151:com.baz U=0 visible=true mode=fullscreen translucent=false sz=1}
+    topResumedActivity=ActivityRecord{f2e59ab u0 com.baz/com.baz.MainActivity t41}
+    * Hist[0] ActivityRecord{f2e59ab u0 com.baz/com.baz.MainActivity t41}
+  * Task{3dc6f0c #1 type=home ?? U=0 visible=false mode=fullscreen translucent=false sz=1}
+    mLastPausedActivity: ActivityRecord{c1d u0 com.google.android.apps.nexuslauncher/.NexusLauncherActivity t2}
+
+  Resumed activities in task display areas (from top to bottom):
+    Resumed: ActivityRecord{f2e59ab u0 com.baz/com.baz.MainActivity t41}
+
+ ResumedActivity: ActivityRecord{f2e59ab u0 com.baz/com.baz.MainActivity t41}
+";
+
+    const ANDROID_14_SLEEPING: &str = "ACTIVITY MANAGER ACTIVITIES (dumpsys activity activities)
+Display #0 (activities from top to bottom):
+  * Task{3dc6f0c #1 type=home U=0 visible=false mode=fullscreen translucent=false sz=1}
+    topResumedActivity=null
+    mLastPausedActivity: ActivityRecord{c1d u0 com.google.android.apps.nexuslauncher/.NexusLauncherActivity t2}
+";
+
+    const WINDOW: &str = "WINDOW MANAGER WINDOWS (dumpsys window windows)
+  Window #8 Window{e5b6a1c u0 StatusBar}:
+    mDisplayId=0 rootTaskId=1 mSession=Session{2b5 1234:u0a10110} mClient=android.os.BinderProxy@8e7
+  mGlobalConfiguration={1.0 ?mcc?mnc [en_US] ldltr sw411dp w411dp h842dp 420dpi nrml long port finger -keyb/v/h -nav/h winConfig={ mBounds=Rect(0, 0 - 1080, 2340)}}
+  mHasPermanentDpad=false
+  mTopFocusedDisplayId=0
+  mCurrentFocus=Window{8d0f7b3 u0 com.qux/com.qux.MainActivity}
+  mFocusedApp=ActivityRecord{f2e59ab u0 com.qux/com.qux.MainActivity t41}
+";
+
+    const WINDOW_POPUP: &str = "WINDOW MANAGER WINDOWS (dumpsys window windows)
+  mCurrentFocus=Window{1a u0 PopupWindow:9c5e}
+  mFocusedApp=AppWindowToken{4f0 token=Token{9d2 ActivityRecord{f2e u0 com.qux/.MainActivity t41}}}
+";
+
+    #[test]
+    fn should_find_resumed_package() {
+        assert_eq!(resumed_package(ANDROID_7), Some("com.foo"));
+        assert_eq!(resumed_package(ANDROID_10), Some("com.bar"));
+        assert_eq!(resumed_package(ANDROID_12), Some("com.baz"));
+        assert_eq!(resumed_package(ANDROID_14_SLEEPING), None);
+        assert_eq!(resumed_package("* TaskRecord{8e3c2f0 #57 A=com.legacy U=0 sz=1}"), Some("com.legacy"));
+    }
+
+    #[test]
+    fn should_find_focused_package() {
+        assert_eq!(focused_package(WINDOW), Some("com.qux"));
+        assert_eq!(focused_package(WINDOW_POPUP), Some("com.qux"));
+        assert_eq!(focused_package("  mCurrentFocus=null\n  mFocusedApp=null"), None);
+    }
+}
@@ -0,0 +1,292 @@
+use crate::{parse, Kind, LogCatLine};
+pub use crate::parser::Level;
+use crate::binary::{self, Buffer};
+use crate::events::{Event, EventTags};
+
+use core::convert::TryFrom;
+use std::io::{self, BufRead};
+
+#[derive(Debug, Clone, PartialEq)]
+///Owned log entry, which can span multiple lines.
+pub struct LogEntry {
+    ///None if format has no time or time is since boot.
+    pub time: Option<time::OffsetDateTime>,
+    ///Uid or its name, if present in format.
+    pub uid: Option<String>,
+    pub pid: Option<u32>,
+    pub tid: Option<u32>,
+    pub level: Level,
+    pub tag: String,
+    pub msg: String,
+    ///Source buffer, known only for binary logcat output.
+    pub buffer: Option<Buffer>,
+    ///Decoded event, known only for binary logcat output of events buffer.
+    pub event: Option<Event>,
+}
+
+//Parses fraction of second into nanoseconds.
+fn parse_nanos(fraction: &str) -> Option<u32> {
+    if fraction.len() > 9 || !fraction.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+
+    let mut nanos = 0;
+    for idx in 0..9 {
+        nanos = nanos * 10 + fraction.as_bytes().get(idx).map(|digit| u32::from(digit - b'0')).unwrap_or(0);
+    }
+    Some(nanos)
+}
+
+//Parses zone modifier, i.e. `+0100`
+fn parse_zone(zone: &str) -> Option<time::UtcOffset> {
+    let sign = match zone.as_bytes().first()? {
+        b'+' => 1,
+        b'-' => -1,
+        _ => return None,
+    };
+    let hours: i8 = zone.get(1..3)?.parse().ok()?;
+    let minutes: i8 = zone.get(3..5)?.parse().ok()?;
+    time::UtcOffset::from_hms(sign * hours, sign * minutes, 0).ok()
+}
+
+fn parse_time(line: &LogCatLine<'_>, offset: time::UtcOffset, year: i32) -> Option<time::OffsetDateTime> {
+    if line.format.monotonic || line.time.is_empty() {
+        return None;
+    }
+
+    let (time, fraction) = match line.time.find('.') {
+        Some(idx) => (&line.time[..idx], &line.time[idx + 1..]),
+        None => (line.time, ""),
+    };
+    let nanos = parse_nanos(fraction)?;
+
+    if line.format.epoch {
+        let datetime = time::OffsetDateTime::from_unix_timestamp(time.parse().ok()?).ok()?;
+        return Some((datetime + time::Duration::nanoseconds(i64::from(nanos))).to_offset(offset));
+    }
+
+    let mut date = line.date.split('-').rev();
+    let day = date.next()?.parse().ok()?;
+    let month: u8 = date.next()?.parse().ok()?;
+    let year = match date.next() {
+        Some(year) => year.parse().ok()?,
+        None => year,
+    };
+    let date = time::Date::from_calendar_date(year, time::Month::try_from(month).ok()?, day).ok()?;
+
+    let mut time = time.split(':');
+    let hour = time.next()?.parse().ok()?;
+    let minute = time.next()?.parse().ok()?;
+    let second = time.next()?.parse().ok()?;
+    let time = time::Time::from_hms_nano(hour, minute, second, nanos).ok()?;
+
+    let offset = match line.zone.is_empty() {
+        true => offset,
+        false => parse_zone(line.zone)?,
+    };
+    Some(time::PrimitiveDateTime::new(date, time).assume_offset(offset))
+}
+
+impl LogEntry {
+    ///Creates entry from parsed line, returning none if its level is unknown.
+    ///
+    ///Time without zone is assumed to be at `offset`, and date without year is assumed to be of `year`.
+    pub fn from_line(line: &LogCatLine<'_>, offset: time::UtcOffset, year: i32) -> Option<Self> {
+        Some(Self {
+            time: parse_time(line, offset, year),
+            uid: match line.uid.is_empty() {
+                true => None,
+                false => Some(line.uid.to_owned()),
+            },
+            pid: line.pid,
+            tid: line.tid,
+            level: Level::from_letter(line.level)?,
+            tag: line.tag.to_owned(),
+            msg: line.msg.to_owned(),
+            buffer: line.buffer,
+            event: line.event.cloned(),
+        })
+    }
+
+    ///Creates entry from binary logcat output, returning none if its level is unknown.
+    pub fn from_binary(entry: &binary::Entry, tags: Option<&EventTags>) -> Option<Self> {
+        let text = entry.text(tags);
+        Some(Self {
+            time: Some(entry.datetime(time::UtcOffset::UTC)),
+            uid: entry.uid.map(|uid| uid.to_string()),
+            pid: Some(entry.pid),
+            tid: Some(entry.tid),
+            level: Level::from_letter(text.level)?,
+            tag: text.tag.into_owned(),
+            msg: text.msg.into_owned(),
+            buffer: entry.buffer,
+            event: text.event,
+        })
+    }
+}
+
+///Reads log entries from logcat output.
+///
+///Unrecognized lines are skipped, while message lines of `long` format are joined into single entry.
+pub struct LogReader<R> {
+    input: R,
+    line: Vec<u8>,
+    //Entry of `long` format, which is complete on empty line.
+    pending: Option<LogEntry>,
+    ///Whether input is binary logcat output (i.e. `logcat -B`). By default false.
+    pub binary: bool,
+    ///Offset of time without zone. By default local offset, if it can be determined.
+    pub offset: time::UtcOffset,
+    ///Year of date without year. By default current year.
+    pub year: i32,
+    ///Event tags to decode events of binary logcat, by default none.
+    pub event_tags: Option<EventTags>,
+}
+
+impl<R: BufRead> LogReader<R> {
+    pub fn new(input: R) -> Self {
+        let now = time::OffsetDateTime::now_local().unwrap_or_else(|_| time::OffsetDateTime::now_utc());
+        Self {
+            input,
+            line: Vec::new(),
+            pending: None,
+            binary: false,
+            offset: now.offset(),
+            year: now.year(),
+            event_tags: None,
+        }
+    }
+
+    fn next_binary(&mut self) -> Option<io::Result<LogEntry>> {
+        loop {
+            match binary::read_entry(&mut self.input) {
+                Ok(Some(entry)) => if let Some(entry) = LogEntry::from_binary(&entry, self.event_tags.as_ref()) {
+                    return Some(Ok(entry));
+                },
+                Ok(None) => return None,
+                Err(error) => return Some(Err(error)),
+            }
+        }
+    }
+
+    fn next_text(&mut self) -> Option<io::Result<LogEntry>> {
+        loop {
+            self.line.clear();
+            match self.input.read_until(b'\n', &mut self.line) {
+                Ok(0) => return self.pending.take().map(Ok),
+                Ok(_) => (),
+                Err(error) => return Some(Err(error)),
+            }
+
+            let line = String::from_utf8_lossy(&self.line);
+            match parse(&line) {
+                Some(header) if header.format.kind == Kind::Long => {
+                    let entry = LogEntry::from_line(&header, self.offset, self.year);
+                    if let Some(entry) = core::mem::replace(&mut self.pending, entry) {
+                        return Some(Ok(entry));
+                    }
+                },
+                _ if self.pending.is_some() => {
+                    let msg = line.trim_end();
+                    if msg.is_empty() {
+                        return self.pending.take().map(Ok);
+                    }
+
+                    if let Some(entry) = self.pending.as_mut() {
+                        if !entry.msg.is_empty() {
+                            entry.msg.push('\n');
+                        }
+                        entry.msg.push_str(msg);
+                    }
+                },
+                Some(line) => if let Some(entry) = LogEntry::from_line(&line, self.offset, self.year) {
+                    return Some(Ok(entry));
+                },
+                None => (),
+            }
+        }
+    }
+}
+
+impl LogReader<io::BufReader<std::process::ChildStdout>> {
+    ///Creates reader of adb child, taking its stdout, which must be piped.
+    pub fn from_child(child: &mut std::process::Child) -> Option<Self> {
+        child.stdout.take().map(|stdout| Self::new(io::BufReader::new(stdout)))
+    }
+}
+
+impl<R: BufRead> Iterator for LogReader<R> {
+    type Item = io::Result<LogEntry>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.binary {
+            true => self.next_binary(),
+            false => self.next_text(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Level, LogReader};
+    use crate::binary::Buffer;
+
+    #[test]
+    fn should_read_text_entries() {
+        let input = "--------- beginning of main
+12-02 10:00:00.123  100  101 I Tag: first
+garbage
+2021-12-02 10:00:01.000000 +0100  u0_a85  100  101 W Tag: second
+
+[ 12-02 10:00:02.000   100:  101 E/Tag ]
+line one
+line two
+
+1600000000.5  100  101 V Tag: third
+";
+
+        let mut reader = LogReader::new(input.as_bytes());
+        reader.offset = time::UtcOffset::UTC;
+        reader.year = 2020;
+        let entries = reader.collect::<Result<Vec<_>, _>>().expect("To read");
+        assert_eq!(entries.len(), 4);
+
+        assert_eq!(entries[0].time, Some(time::macros::datetime!(2020-12-02 10:00:00.123 UTC)));
+        assert_eq!((entries[0].pid, entries[0].tid, entries[0].level), (Some(100), Some(101), Level::Info));
+        assert_eq!((entries[0].tag.as_str(), entries[0].msg.as_str()), ("Tag", "first"));
+
+        assert_eq!(entries[1].time, Some(time::macros::datetime!(2021-12-02 10:00:01 +01:00)));
+        assert_eq!(entries[1].uid.as_deref(), Some("u0_a85"));
+        assert_eq!(entries[1].level, Level::Warn);
+
+        assert_eq!(entries[2].level, Level::Error);
+        assert_eq!(entries[2].msg, "line one\nline two");
+
+        assert_eq!(entries[3].time, Some(time::macros::datetime!(2020-09-13 12:26:40.5 UTC)));
+        assert!(Level::Verbose < Level::Fatal);
+        assert_eq!(Level::from_letter("A"), Some(Level::Fatal));
+    }
+
+    #[test]
+    fn should_read_binary_entries() {
+        let payload = b"\x05Tag\0first\nsecond\0";
+        let mut input = Vec::new();
+        input.extend_from_slice(&(payload.len() as u16).to_le_bytes());
+        input.extend_from_slice(&28u16.to_le_bytes());
+        for field in &[100u32, 101, 1_600_000_000, 0, 3, 1000] {
+            input.extend_from_slice(&field.to_le_bytes());
+        }
+        input.extend_from_slice(payload);
+
+        let mut reader = LogReader::new(&input[..]);
+        reader.binary = true;
+        let entry = reader.next().expect("To have entry").expect("To read");
+        assert_eq!(entry.level, Level::Warn);
+        assert_eq!(entry.msg, "first\nsecond");
+        assert_eq!(entry.buffer, Some(Buffer::System));
+        assert_eq!(entry.uid.as_deref(), Some("1000"));
+        assert_eq!(entry.time, Some(time::macros::datetime!(2020-09-13 12:26:40 UTC)));
+        assert!(reader.next().is_none());
+    }
+}
@@ -1,4 +1,5 @@
 pub const DUMPSYS_FAIL: isize = 2;
 pub const ADB_FAIL: isize = 3;
 pub const UTF8_ERROR: isize = 4;
+pub const IO_FAIL: isize = 5;
 pub const INTERNAL: isize = 100;
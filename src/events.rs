@@ -0,0 +1,274 @@
+use core::fmt;
+use std::collections::HashMap;
+
+//Types of event values.
+const TYPE_INT: u8 = 0;
+const TYPE_LONG: u8 = 1;
+const TYPE_STRING: u8 = 2;
+const TYPE_LIST: u8 = 3;
+const TYPE_FLOAT: u8 = 4;
+//Lists are not expected to be nested deeply, so anything deeper is considered malformed.
+const MAX_DEPTH: usize = 8;
+
+#[derive(Debug, Clone, PartialEq)]
+///Value of event.
+pub enum Value {
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    String(String),
+    List(Vec<Value>),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Int(value) => fmt::Display::fmt(value, fmt),
+            Value::Long(value) => fmt::Display::fmt(value, fmt),
+            Value::Float(value) => fmt::Display::fmt(value, fmt),
+            Value::String(value) => fmt.write_str(value),
+            Value::List(values) => {
+                fmt.write_str("[")?;
+                for (idx, value) in values.iter().enumerate() {
+                    if idx > 0 {
+                        fmt.write_str(",")?;
+                    }
+                    fmt::Display::fmt(value, fmt)?;
+                }
+                fmt.write_str("]")
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+///Decoded event, i.e. entry of `events` buffer.
+pub struct Event {
+    ///Name of tag, or its number if tag is unknown.
+    pub name: String,
+    ///Values of event with names of fields, which are empty if not described.
+    pub fields: Vec<(String, Value)>,
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.write_str(&self.name)?;
+        fmt.write_str("(")?;
+        for (idx, (name, value)) in self.fields.iter().enumerate() {
+            if idx > 0 {
+                fmt.write_str(", ")?;
+            }
+            if !name.is_empty() {
+                fmt.write_str(name)?;
+                fmt.write_str("=")?;
+            }
+            fmt::Display::fmt(value, fmt)?;
+        }
+        fmt.write_str(")")
+    }
+}
+
+//Reads value, returning it with remaining bytes.
+fn read_value(data: &[u8], depth: usize) -> Option<(Value, &[u8])> {
+    let (kind, data) = data.split_first()?;
+    match *kind {
+        TYPE_INT if data.len() >= 4 => Some((Value::Int(i32::from_le_bytes([data[0], data[1], data[2], data[3]])), &data[4..])),
+        TYPE_LONG if data.len() >= 8 => {
+            let mut value = [0; 8];
+            value.copy_from_slice(&data[..8]);
+            Some((Value::Long(i64::from_le_bytes(value)), &data[8..]))
+        },
+        TYPE_FLOAT if data.len() >= 4 => Some((Value::Float(f32::from_le_bytes([data[0], data[1], data[2], data[3]])), &data[4..])),
+        TYPE_STRING if data.len() >= 4 => {
+            let len = u32::from_le_bytes([data[0], data[1], data[2], data[3]]) as usize;
+            let text = data[4..].get(..len)?;
+            Some((Value::String(String::from_utf8_lossy(text).into_owned()), &data[4 + len..]))
+        },
+        TYPE_LIST if depth < MAX_DEPTH => {
+            let (count, mut data) = data.split_first()?;
+            let mut values = Vec::with_capacity(*count as usize);
+            for _ in 0..*count {
+                let (value, rest) = read_value(data, depth + 1)?;
+                values.push(value);
+                data = rest;
+            }
+            Some((Value::List(values), data))
+        },
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone)]
+///Description of event tag.
+pub struct Tag {
+    pub name: String,
+    ///Names of fields, which can be empty if tag has no description.
+    pub fields: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+///Event tags, as described by `/system/etc/event-log-tags`
+pub struct EventTags {
+    tags: HashMap<u32, Tag>,
+}
+
+impl EventTags {
+    ///Parses content of `event-log-tags`, ignoring unrecognized lines.
+    ///
+    ///Each tag is described as `30014 am_proc_start (User|1|5),(PID|1|5),(Process Name|3)`
+    pub fn parse(text: &str) -> Self {
+        let mut tags = HashMap::new();
+
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut parts = line.splitn(3, char::is_whitespace);
+            let id = match parts.next().and_then(|id| id.parse().ok()) {
+                Some(id) => id,
+                None => continue,
+            };
+            let name = match parts.next() {
+                Some(name) if !name.is_empty() => name,
+                _ => continue,
+            };
+
+            let mut fields = Vec::new();
+            let mut descriptions = parts.next().unwrap_or("");
+            while let Some(start) = descriptions.find('(') {
+                let end = match descriptions[start..].find(')') {
+                    Some(end) => start + end,
+                    None => break,
+                };
+                let description = &descriptions[start + 1..end];
+                fields.push(description.split('|').next().unwrap_or(description).trim().to_owned());
+                descriptions = &descriptions[end + 1..];
+            }
+
+            tags.insert(id, Tag {
+                name: name.to_owned(),
+                fields,
+            });
+        }
+
+        Self {
+            tags,
+        }
+    }
+
+    #[inline]
+    ///Returns whether there are no tags.
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+    }
+
+    #[inline]
+    ///Returns description of tag, if known.
+    pub fn get(&self, id: u32) -> Option<&Tag> {
+        self.tags.get(&id)
+    }
+}
+
+///Decodes payload of event, which is tag number followed by value.
+///
+///Values are named after fields of tag, if tags are provided and tag is known.
+///Returns none if payload is malformed.
+pub fn decode(payload: &[u8], tags: Option<&EventTags>) -> Option<Event> {
+    let id = u32::from_le_bytes([*payload.get(0)?, *payload.get(1)?, *payload.get(2)?, *payload.get(3)?]);
+    let tag = tags.and_then(|tags| tags.get(id));
+    let name = match tag {
+        Some(tag) => tag.name.clone(),
+        None => id.to_string(),
+    };
+    let names: &[String] = match tag {
+        Some(tag) => &tag.fields,
+        None => &[],
+    };
+
+    let data = &payload[4..];
+    let value = match data.is_empty() {
+        true => None,
+        false => Some(read_value(data, 0)?.0),
+    };
+
+    //List is spread across fields, unless it is described as single field.
+    let values = match value {
+        Some(Value::List(values)) if names.len() != 1 => values,
+        Some(value) => vec![value],
+        None => Vec::new(),
+    };
+
+    let fields = values.into_iter().enumerate().map(|(idx, value)| match names.get(idx) {
+        Some(name) => (name.clone(), value),
+        None => (String::new(), value),
+    }).collect();
+
+    Some(Event {
+        name,
+        fields,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode, EventTags, Value};
+
+    const TAGS: &str = "# The entries in this file map a sparse set of log tag numbers to tag names.
+42 answer (to life the universe etc|3)
+2722 battery_level (level|1|6),(voltage|1|1),(temperature|1|1)
+30014 am_proc_start (User|1|5),(PID|1|5),(UID|1|5),(Process Name|3),(Type|3),(Component|3)
+";
+
+    fn list(values: &[&[u8]]) -> Vec<u8> {
+        let mut result = vec![3, values.len() as u8];
+        for value in values {
+            result.extend_from_slice(value);
+        }
+        result
+    }
+
+    fn int(value: i32) -> Vec<u8> {
+        let mut result = vec![0];
+        result.extend_from_slice(&value.to_le_bytes());
+        result
+    }
+
+    fn string(value: &str) -> Vec<u8> {
+        let mut result = vec![2];
+        result.extend_from_slice(&(value.len() as u32).to_le_bytes());
+        result.extend_from_slice(value.as_bytes());
+        result
+    }
+
+    #[test]
+    fn should_decode_events() {
+        let tags = EventTags::parse(TAGS);
+        assert_eq!(tags.get(42).unwrap().fields, ["to life the universe etc"]);
+
+        let mut payload = 30014u32.to_le_bytes().to_vec();
+        payload.extend(list(&[&int(0), &int(1200), &int(10085), &string("com.foo"), &string("activity"), &string("{com.foo/com.foo.MainActivity}")]));
+        let event = decode(&payload, Some(&tags)).expect("To decode");
+        assert_eq!(event.to_string(), "am_proc_start(User=0, PID=1200, UID=10085, Process Name=com.foo, Type=activity, Component={com.foo/com.foo.MainActivity})");
+
+        let event = decode(&payload, None).expect("To decode");
+        assert_eq!(event.to_string(), "30014(0, 1200, 10085, com.foo, activity, {com.foo/com.foo.MainActivity})");
+
+        let mut payload = 42u32.to_le_bytes().to_vec();
+        payload.extend(list(&[&int(1), &string("two")]));
+        let event = decode(&payload, Some(&tags)).expect("To decode");
+        assert_eq!(event.fields, [("to life the universe etc".to_owned(), Value::List(vec![Value::Int(1), Value::String("two".to_owned())]))]);
+        assert_eq!(event.to_string(), "answer(to life the universe etc=[1,two])");
+
+        let mut payload = 2722u32.to_le_bytes().to_vec();
+        payload.extend(&[1, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]);
+        assert_eq!(decode(&payload, Some(&tags)).expect("To decode").to_string(), "battery_level(level=-1)");
+
+        let mut payload = 2722u32.to_le_bytes().to_vec();
+        payload.extend(&[3, 2, 4]);
+        payload.extend(&1.5f32.to_le_bytes());
+        assert!(decode(&payload, Some(&tags)).is_none());
+        assert!(decode(&[1, 2], Some(&tags)).is_none());
+    }
+}
@@ -0,0 +1,447 @@
+use crate::{LogCatLine, Level};
+
+use core::fmt;
+
+#[derive(Debug)]
+///Filter expression error.
+pub struct ParseError(String);
+
+impl fmt::Display for ParseError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+macro_rules! error {
+    ($($arg:tt)*) => {
+        return Err(ParseError(format!($($arg)*)))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token<'a> {
+    Open,
+    Close,
+    Not,
+    And,
+    Or,
+    Op(Op),
+    Word(&'a str),
+    Str(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Eq,
+    NotEq,
+    Match,
+    NotMatch,
+    Contains,
+    Greater,
+    GreaterEq,
+    Less,
+    LessEq,
+}
+
+impl Op {
+    const fn as_str(self) -> &'static str {
+        match self {
+            Op::Eq => "==",
+            Op::NotEq => "!=",
+            Op::Match => "~",
+            Op::NotMatch => "!~",
+            Op::Contains => "contains",
+            Op::Greater => ">",
+            Op::GreaterEq => ">=",
+            Op::Less => "<",
+            Op::LessEq => "<=",
+        }
+    }
+
+    fn cmp(self, ordering: core::cmp::Ordering) -> bool {
+        use core::cmp::Ordering;
+
+        match self {
+            Op::Eq => ordering == Ordering::Equal,
+            Op::NotEq => ordering != Ordering::Equal,
+            Op::Greater => ordering == Ordering::Greater,
+            Op::GreaterEq => ordering != Ordering::Less,
+            Op::Less => ordering == Ordering::Less,
+            Op::LessEq => ordering != Ordering::Greater,
+            Op::Match | Op::NotMatch | Op::Contains => false,
+        }
+    }
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token<'_>>, ParseError> {
+    const SPECIAL: &[char] = &['(', ')', '!', '&', '|', '=', '~', '<', '>', '"'];
+
+    let mut tokens = Vec::new();
+    let mut cursor = text.trim_start();
+
+    while let Some(ch) = cursor.chars().next() {
+        let (token, len) = match ch {
+            '(' => (Token::Open, 1),
+            ')' => (Token::Close, 1),
+            '~' => (Token::Op(Op::Match), 1),
+            '!' if cursor.starts_with("!=") => (Token::Op(Op::NotEq), 2),
+            '!' if cursor.starts_with("!~") => (Token::Op(Op::NotMatch), 2),
+            '!' => (Token::Not, 1),
+            '&' if cursor.starts_with("&&") => (Token::And, 2),
+            '|' if cursor.starts_with("||") => (Token::Or, 2),
+            '=' if cursor.starts_with("==") => (Token::Op(Op::Eq), 2),
+            '>' if cursor.starts_with(">=") => (Token::Op(Op::GreaterEq), 2),
+            '>' => (Token::Op(Op::Greater), 1),
+            '<' if cursor.starts_with("<=") => (Token::Op(Op::LessEq), 2),
+            '<' => (Token::Op(Op::Less), 1),
+            '"' => {
+                let mut value = String::new();
+                let mut chars = cursor.char_indices().skip(1);
+                let len = loop {
+                    match chars.next() {
+                        Some((_, '\\')) => match chars.next() {
+                            Some((_, ch)) => value.push(ch),
+                            None => error!("Unterminated string"),
+                        },
+                        Some((idx, '"')) => break idx + 1,
+                        Some((_, ch)) => value.push(ch),
+                        None => error!("Unterminated string"),
+                    }
+                };
+                (Token::Str(value), len)
+            },
+            ch if SPECIAL.contains(&ch) => error!("Unexpected character '{}'", ch),
+            _ => {
+                let len = cursor.find(|ch: char| ch.is_whitespace() || SPECIAL.contains(&ch)).unwrap_or(cursor.len());
+                match &cursor[..len] {
+                    "contains" => (Token::Op(Op::Contains), len),
+                    word => (Token::Word(word), len),
+                }
+            },
+        };
+
+        tokens.push(token);
+        cursor = cursor[len..].trim_start();
+    }
+
+    Ok(tokens)
+}
+
+#[derive(Debug)]
+enum Value {
+    Text(String),
+    Regex(regex::Regex),
+    Number(u32),
+    Level(Level),
+    ///Nanoseconds, as returned by `parse_time`
+    Time(u64),
+}
+
+//Parses time into nanoseconds, either since midnight (`HH:MM[:SS][.fff]`) or since Epoch/boot (`SSSS[.fff]`).
+fn parse_time(time: &str) -> Option<u64> {
+    let (time, fraction) = match time.find('.') {
+        Some(idx) => (&time[..idx], &time[idx + 1..]),
+        None => (time, ""),
+    };
+    if fraction.len() > 9 || !fraction.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+
+    let mut seconds = 0u64;
+    let mut parts = 0;
+    for part in time.split(':') {
+        if part.is_empty() || !part.bytes().all(|byte| byte.is_ascii_digit()) {
+            return None;
+        }
+        seconds = seconds.checked_mul(60)?.checked_add(part.parse().ok()?)?;
+        parts += 1;
+    }
+    seconds = match parts {
+        1 | 3 => seconds,
+        //`HH:MM`
+        2 => seconds.checked_mul(60)?,
+        _ => return None,
+    };
+
+    let mut nanos = 0;
+    for idx in 0..9 {
+        nanos = nanos * 10 + fraction.as_bytes().get(idx).map(|digit| u64::from(digit - b'0')).unwrap_or(0);
+    }
+    seconds.checked_mul(1_000_000_000)?.checked_add(nanos)
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Field {
+    Tag,
+    Level,
+    Pid,
+    Tid,
+    Msg,
+    Time,
+}
+
+#[derive(Debug)]
+enum Expr {
+    Or(Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Cmp(Field, Op, Value),
+}
+
+impl Expr {
+    fn matches(&self, line: &LogCatLine<'_>) -> bool {
+        match self {
+            Expr::Or(left, right) => left.matches(line) || right.matches(line),
+            Expr::And(left, right) => left.matches(line) && right.matches(line),
+            Expr::Not(expr) => !expr.matches(line),
+            Expr::Cmp(field, op, value) => {
+                let text = match field {
+                    Field::Tag => line.tag,
+                    Field::Msg => line.msg,
+                    Field::Time => match value {
+                        Value::Time(expected) => return match parse_time(line.time) {
+                            Some(time) => op.cmp(time.cmp(expected)),
+                            None => false,
+                        },
+                        _ => line.time,
+                    },
+                    Field::Level => return match (Level::from_letter(line.level), value) {
+                        (Some(level), Value::Level(expected)) => op.cmp(level.cmp(expected)),
+                        _ => false,
+                    },
+                    Field::Pid | Field::Tid => {
+                        let id = match field {
+                            Field::Pid => line.pid,
+                            _ => line.tid,
+                        };
+                        return match (id, value) {
+                            (Some(id), Value::Number(expected)) => op.cmp(id.cmp(expected)),
+                            _ => false,
+                        };
+                    },
+                };
+
+                match (op, value) {
+                    (Op::Match, Value::Regex(regex)) => regex.is_match(text),
+                    (Op::NotMatch, Value::Regex(regex)) => !regex.is_match(text),
+                    (Op::Contains, Value::Text(expected)) => text.contains(expected.as_str()),
+                    (op, Value::Text(expected)) => op.cmp(text.cmp(expected.as_str())),
+                    _ => false,
+                }
+            }
+        }
+    }
+}
+
+struct Parser<'a> {
+    tokens: Vec<Token<'a>>,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    #[inline]
+    fn next(&mut self) -> Option<Token<'a>> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    #[inline]
+    fn peek(&self) -> Option<&Token<'a>> {
+        self.tokens.get(self.pos)
+    }
+
+    fn or(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.and()?;
+        while let Some(Token::Or) = self.peek() {
+            self.pos += 1;
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.unary()?;
+        while let Some(Token::And) = self.peek() {
+            self.pos += 1;
+            expr = Expr::And(Box::new(expr), Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr, ParseError> {
+        match self.next() {
+            Some(Token::Not) => Ok(Expr::Not(Box::new(self.unary()?))),
+            Some(Token::Open) => {
+                let expr = self.or()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(expr),
+                    _ => error!("Missing closing parenthesis"),
+                }
+            },
+            Some(Token::Word(field)) => self.cmp(field),
+            Some(token) => error!("Expected field, got {:?}", token),
+            None => error!("Unexpected end of expression"),
+        }
+    }
+
+    fn cmp(&mut self, field: &str) -> Result<Expr, ParseError> {
+        let field = match field {
+            "tag" => Field::Tag,
+            "level" => Field::Level,
+            "pid" => Field::Pid,
+            "tid" => Field::Tid,
+            "msg" | "message" => Field::Msg,
+            "time" => Field::Time,
+            field => error!("Unknown field '{}'", field),
+        };
+
+        let op = match self.next() {
+            Some(Token::Op(op)) => op,
+            _ => error!("Expected comparison operator after {:?}", field),
+        };
+
+        let value = match self.next() {
+            Some(Token::Str(value)) => value,
+            Some(Token::Word(value)) => value.to_owned(),
+            _ => error!("Expected value after '{}'", op.as_str()),
+        };
+
+        let value = match field {
+            Field::Tag | Field::Msg | Field::Time => match op {
+                Op::Match | Op::NotMatch => match regex::Regex::new(&value) {
+                    Ok(regex) => Value::Regex(regex),
+                    Err(error) => error!("Invalid regex '{}': {}", value, error),
+                },
+                //Time is compared as number, as its text differs in width and precision between formats.
+                Op::Eq | Op::NotEq | Op::Greater | Op::GreaterEq | Op::Less | Op::LessEq if matches!(field, Field::Time) => match parse_time(&value) {
+                    Some(time) => Value::Time(time),
+                    None if matches!(op, Op::Eq | Op::NotEq) => Value::Text(value),
+                    None => error!("Invalid time '{}'", value),
+                },
+                _ => Value::Text(value),
+            },
+            _ if matches!(op, Op::Match | Op::NotMatch | Op::Contains) => error!("'{}' cannot be used with {:?}", op.as_str(), field),
+            Field::Level => match Level::from_letter(&value) {
+                Some(level) => Value::Level(level),
+                None => error!("Invalid level '{}'", value),
+            },
+            Field::Pid | Field::Tid => match value.parse() {
+                Ok(value) => Value::Number(value),
+                Err(_) => error!("Invalid {:?} '{}'", field, value),
+            },
+        };
+
+        Ok(Expr::Cmp(field, op, value))
+    }
+}
+
+#[derive(Debug)]
+///Boolean filter expression over log line fields.
+///
+///Supported fields: `tag`, `level`, `pid`, `tid`, `msg` and `time`.
+///
+///`time` is compared as number, either `HH:MM[:SS][.fff]` or seconds since Epoch/boot.
+///
+///Supported operators: `==`, `!=`, `>`, `>=`, `<`, `<=`, `~` (regex match), `!~` and `contains`.
+///
+///Expressions can be combined with `&&`, `||`, `!` and grouped with parentheses.
+///`&&` takes precedence over `||`.
+///
+///Example: `(tag ~ "^Net" && level >= W) || msg contains "timeout" && !(pid == 1234)`
+pub struct Filter {
+    expr: Expr,
+}
+
+impl Filter {
+    #[inline]
+    ///Returns whether line satisfies filter.
+    pub fn matches(&self, line: &LogCatLine<'_>) -> bool {
+        self.expr.matches(line)
+    }
+}
+
+impl core::str::FromStr for Filter {
+    type Err = ParseError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            tokens: tokenize(text)?,
+            pos: 0,
+        };
+
+        let expr = parser.or()?;
+        match parser.peek() {
+            Some(token) => error!("Unexpected {:?} at the end of expression", token),
+            None => Ok(Self {
+                expr
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Filter;
+    use crate::parse;
+
+    #[test]
+    fn should_evaluate_filter() {
+        let filter: Filter = r#"(tag ~ "^Net" && level >= W) || msg contains "timeout" && !(pid == 1234)"#.parse().expect("To parse filter");
+
+        let line = parse("12-02 24:01:13.237   666   667 W Network : failed").expect("To parse");
+        assert!(filter.matches(&line));
+        let line = parse("12-02 24:01:13.237   666   667 I Network : failed").expect("To parse");
+        assert!(!filter.matches(&line));
+        let line = parse("12-02 24:01:13.237   666   667 I flutter : timeout").expect("To parse");
+        assert!(filter.matches(&line));
+        let line = parse("12-02 24:01:13.237  1234   667 I flutter : timeout").expect("To parse");
+        assert!(!filter.matches(&line));
+    }
+
+    #[test]
+    fn should_compare_fields() {
+        let line = parse("12-02 24:01:13.237   666   667 W flutter : my super log").expect("To parse");
+
+        let filter: Filter = "tid > 600 && tid <= 667 && tag == flutter && time >= \"24:00\"".parse().expect("To parse filter");
+        assert!(filter.matches(&line));
+        let filter: Filter = "level < w || tag != flutter || msg !~ super".parse().expect("To parse filter");
+        assert!(!filter.matches(&line));
+    }
+
+    #[test]
+    fn should_compare_time_as_number() {
+        let line = parse("1600000000.5  100  101 I Tag: epoch").expect("To parse");
+        let filter: Filter = "time > 999999999.9 && time < 1600000000.500001".parse().expect("To parse filter");
+        assert!(filter.matches(&line));
+
+        let line = parse("2021-12-02 10:00:01.000123 +0100  u0_a85  100  101 W Tag: usec").expect("To parse");
+        let filter: Filter = "time > \"10:00:01.0001\" && time < \"10:00:01.0002\" && time == \"10:00:01.000123000\"".parse().expect("To parse filter");
+        assert!(filter.matches(&line));
+        let filter: Filter = "time >= \"10:01\"".parse().expect("To parse filter");
+        assert!(!filter.matches(&line));
+
+        let line = parse("I/Tag: without time").expect("To parse");
+        let filter: Filter = "time < 10:00".parse().expect("To parse filter");
+        assert!(!filter.matches(&line));
+    }
+
+    #[test]
+    fn should_reject_invalid_filter() {
+        assert!("tag".parse::<Filter>().is_err());
+        assert!("tag == ".parse::<Filter>().is_err());
+        assert!("name == flutter".parse::<Filter>().is_err());
+        assert!("(tag == flutter".parse::<Filter>().is_err());
+        assert!("tag == flutter)".parse::<Filter>().is_err());
+        assert!("tag == \"flutter".parse::<Filter>().is_err());
+        assert!("level >= X".parse::<Filter>().is_err());
+        assert!("time > yesterday".parse::<Filter>().is_err());
+        assert!("time < 10:00:00:00".parse::<Filter>().is_err());
+        assert!("pid contains 1".parse::<Filter>().is_err());
+        assert!("msg ~ \"(\"".parse::<Filter>().is_err());
+        assert!("tag == a & tag == b".parse::<Filter>().is_err());
+    }
+}
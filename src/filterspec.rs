@@ -0,0 +1,147 @@
+use crate::Level;
+
+use std::collections::HashMap;
+
+const DEFAULT_TAG: &str = "*";
+
+#[derive(Debug, Clone)]
+///Logcat filter specification, i.e. `tag:level` pairs with `*` applying to any other tag.
+///
+///Line is included only if its level is equal or above minimum level of its tag.
+pub struct FilterSpec {
+    //None if `*` is not specified, which means include any level.
+    default: Option<Level>,
+    tags: HashMap<String, Level>,
+}
+
+impl FilterSpec {
+    #[inline]
+    ///Creates new spec that includes any tag with level equal or above `level`
+    pub fn new(level: Level) -> Self {
+        Self {
+            default: Some(level),
+            tags: HashMap::new(),
+        }
+    }
+
+    ///Adds spec in format `tag[:level]`, overriding existing one.
+    ///
+    ///Level defaults to `V` if not specified.
+    pub fn add(&mut self, spec: &str) -> Result<(), ()> {
+        let (tag, level) = match spec.rfind(':') {
+            Some(idx) => match Level::from_letter(&spec[idx + 1..]) {
+                Some(level) => (&spec[..idx], level),
+                None => return Err(()),
+            },
+            None => (spec, Level::Verbose),
+        };
+
+        if tag.is_empty() {
+            return Err(());
+        } else if tag == DEFAULT_TAG {
+            self.default = Some(level);
+        } else {
+            self.tags.insert(tag.to_owned(), level);
+        }
+
+        Ok(())
+    }
+
+    ///Merges other spec into self, overriding tags present in both.
+    pub fn extend(&mut self, other: &FilterSpec) {
+        if other.default.is_some() {
+            self.default = other.default;
+        }
+        self.tags.extend(other.tags.iter().map(|(tag, level)| (tag.clone(), *level)));
+    }
+
+    #[inline]
+    ///Returns whether spec includes everything.
+    pub fn is_empty(&self) -> bool {
+        self.default.unwrap_or(Level::Verbose) == Level::Verbose && self.tags.is_empty()
+    }
+
+    ///Returns whether line with specified tag and level is included.
+    ///
+    ///Unknown levels are always included.
+    pub fn is_allowed(&self, tag: &str, level: &str) -> bool {
+        let min = match self.tags.get(tag) {
+            Some(min) => *min,
+            None => self.default.unwrap_or(Level::Verbose),
+        };
+
+        match Level::from_letter(level) {
+            Some(level) => level >= min,
+            None => true,
+        }
+    }
+
+    ///Returns spec in format of `adb logcat` arguments.
+    pub fn to_args(&self) -> Vec<String> {
+        let mut args = self.tags.iter().map(|(tag, level)| format!("{}:{}", tag, level.letter())).collect::<Vec<_>>();
+        args.push(format!("{}:{}", DEFAULT_TAG, self.default.unwrap_or(Level::Verbose).letter()));
+        args
+    }
+}
+
+impl Default for FilterSpec {
+    #[inline(always)]
+    fn default() -> Self {
+        Self {
+            default: None,
+            tags: HashMap::new(),
+        }
+    }
+}
+
+impl core::str::FromStr for FilterSpec {
+    type Err = ();
+
+    ///Parses whitespace separated specs, e.g. `OkHttp:W Flutter:V *:I`
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut result = Self::default();
+        for spec in text.split_whitespace() {
+            result.add(spec)?;
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FilterSpec;
+    use crate::Level;
+
+    #[test]
+    fn should_filter_by_tag_level() {
+        let spec: FilterSpec = "OkHttp:W Flutter:V *:I".parse().expect("To parse");
+        assert!(spec.is_allowed("OkHttp", "W"));
+        assert!(spec.is_allowed("OkHttp", "E"));
+        assert!(!spec.is_allowed("OkHttp", "I"));
+        assert!(spec.is_allowed("Flutter", "V"));
+        assert!(spec.is_allowed("Network", "I"));
+        assert!(!spec.is_allowed("Network", "D"));
+
+        let spec: FilterSpec = "Flutter *:S".parse().expect("To parse");
+        assert!(spec.is_allowed("Flutter", "V"));
+        assert!(!spec.is_allowed("Network", "F"));
+    }
+
+    #[test]
+    fn should_merge_specs() {
+        let mut spec = FilterSpec::new(Level::Warn);
+        spec.extend(&"Flutter:D".parse().expect("To parse"));
+        assert!(spec.is_allowed("Flutter", "D"));
+        assert!(!spec.is_allowed("Network", "I"));
+        assert_eq!(spec.to_args(), ["Flutter:D", "*:W"]);
+
+        spec.extend(&"*:V".parse().expect("To parse"));
+        assert!(spec.is_allowed("Network", "V"));
+    }
+
+    #[test]
+    fn should_reject_invalid_spec() {
+        assert!("Flutter:X".parse::<FilterSpec>().is_err());
+        assert!(":W".parse::<FilterSpec>().is_err());
+    }
+}
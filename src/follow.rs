@@ -0,0 +1,162 @@
+use crate::LogCatLine;
+use crate::filterspec::FilterSpec;
+
+use std::collections::HashSet;
+
+const ACTIVITY_MANAGER: &str = "ActivityManager";
+//`Start proc 12345:com.foo/u0a123 for activity {com.foo/com.foo.MainActivity}`
+const START_REGEX: &str = r"^Start proc (\d+):([^/\s]+)/\S+ for (.+)$";
+//`Start proc com.foo for activity com.foo/.MainActivity: pid=12345 uid=10123 gids={50123}`
+const START_OLD_REGEX: &str = r"^Start proc ([^/\s]+) for (.+?): pid=(\d+)";
+//`Process com.foo (pid 12345) has died: fg TOP`
+const DEATH_REGEX: &str = r"^Process (\S+) \(pid (\d+)\) has died";
+//`Killing 12345:com.foo/u0a123 (adj 900): empty`
+const KILL_REGEX: &str = r"^Killing (\d+):([^/\s]+)/\S+";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+///Change of followed processes.
+pub enum Event {
+    ///Process is started, with pid, process name and reason (e.g. `activity {com.foo/com.foo.MainActivity}`).
+    Start(u32, String, String),
+    ///Process is died, with pid and process name.
+    Death(u32, String),
+}
+
+///Follows processes of application across restarts, using `ActivityManager` logs.
+pub struct AppFollower {
+    package: String,
+    sub_processes: bool,
+    pids: HashSet<u32>,
+    start: regex::Regex,
+    start_old: regex::Regex,
+    death: regex::Regex,
+    kill: regex::Regex,
+}
+
+impl AppFollower {
+    ///Creates new instance to follow `package`, which is already running with `pids`.
+    ///
+    ///Sub-processes (e.g. `com.foo:service`) are followed only if `sub_processes` is set.
+    pub fn new<I: IntoIterator<Item = u32>>(package: String, sub_processes: bool, pids: I) -> Self {
+        Self {
+            package,
+            sub_processes,
+            pids: pids.into_iter().collect(),
+            start: regex::Regex::new(START_REGEX).expect("valid start regex"),
+            start_old: regex::Regex::new(START_OLD_REGEX).expect("valid start regex"),
+            death: regex::Regex::new(DEATH_REGEX).expect("valid death regex"),
+            kill: regex::Regex::new(KILL_REGEX).expect("valid kill regex"),
+        }
+    }
+
+    #[inline]
+    ///Returns whether line of process should be included.
+    ///
+    ///Lines without pid are always included.
+    pub fn is_followed(&self, pid: Option<u32>) -> bool {
+        match pid {
+            Some(pid) => self.pids.contains(&pid),
+            None => true,
+        }
+    }
+
+    ///Returns filter spec to pass to logcat, so that it keeps `ActivityManager` lines needed to follow app.
+    ///
+    ///Original spec should still be applied to received lines.
+    pub fn logcat_spec(spec: &FilterSpec) -> FilterSpec {
+        let mut result = spec.clone();
+        if !result.is_allowed(ACTIVITY_MANAGER, "I") {
+            let _ = result.add(&format!("{}:I", ACTIVITY_MANAGER));
+        }
+        result
+    }
+
+    #[inline]
+    ///Returns currently followed pids.
+    pub fn pids(&self) -> &HashSet<u32> {
+        &self.pids
+    }
+
+    ///Handles `ActivityManager` line, updating followed pids.
+    pub fn handle(&mut self, line: &LogCatLine<'_>) -> Option<Event> {
+        if line.tag != ACTIVITY_MANAGER {
+            return None;
+        }
+
+        let msg = line.msg.trim();
+        let (pid, process, start) = if let Some(captures) = self.start.captures(msg) {
+            (captures[1].parse().ok(), captures[2].to_owned(), Some(captures[3].to_owned()))
+        } else if let Some(captures) = self.start_old.captures(msg) {
+            (captures[3].parse().ok(), captures[1].to_owned(), Some(captures[2].to_owned()))
+        } else if let Some(captures) = self.death.captures(msg) {
+            (captures[2].parse().ok(), captures[1].to_owned(), None)
+        } else if let Some(captures) = self.kill.captures(msg) {
+            (captures[1].parse().ok(), captures[2].to_owned(), None)
+        } else {
+            return None;
+        };
+
+        let pid: u32 = pid?;
+        if !crate::process::is_app_process(&process, &self.package, self.sub_processes) {
+            return None;
+        }
+
+        //Death is reported by multiple lines, so notify only once.
+        match start {
+            Some(reason) => match self.pids.insert(pid) {
+                true => Some(Event::Start(pid, process, reason)),
+                false => None,
+            },
+            None => match self.pids.remove(&pid) {
+                true => Some(Event::Death(pid, process)),
+                false => None,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AppFollower, Event};
+    use crate::parse;
+    use crate::filterspec::FilterSpec;
+
+    #[test]
+    fn should_follow_app_restart() {
+        let mut follower = AppFollower::new("com.foo".to_owned(), true, vec![100]);
+        assert!(follower.is_followed(Some(100)));
+        assert!(!follower.is_followed(Some(200)));
+
+        let line = parse("12-02 24:01:13.237  555  600 I ActivityManager: Process com.foo (pid 100) has died: fg  TOP").expect("To parse");
+        assert_eq!(follower.handle(&line), Some(Event::Death(100, "com.foo".to_owned())));
+        let line = parse("12-02 24:01:13.237  555  600 I ActivityManager: Killing 100:com.foo/u0a123 (adj 0): crash").expect("To parse");
+        assert_eq!(follower.handle(&line), None);
+        assert!(!follower.is_followed(Some(100)));
+
+        let line = parse("12-02 24:01:14.237  555  600 I ActivityManager: Start proc 200:com.foo/u0a123 for activity {com.foo/com.foo.MainActivity}").expect("To parse");
+        assert_eq!(follower.handle(&line), Some(Event::Start(200, "com.foo".to_owned(), "activity {com.foo/com.foo.MainActivity}".to_owned())));
+        assert!(follower.is_followed(Some(200)));
+
+        let line = parse("12-02 24:01:14.237  555  600 I ActivityManager: Start proc com.foo:service for service com.foo/.Sync: pid=300 uid=10123 gids={50123}").expect("To parse");
+        assert_eq!(follower.handle(&line), Some(Event::Start(300, "com.foo:service".to_owned(), "service com.foo/.Sync".to_owned())));
+
+        let line = parse("12-02 24:01:14.237  555  600 I ActivityManager: Start proc 400:com.foobar/u0a124 for activity {com.foobar/com.foobar.MainActivity}").expect("To parse");
+        assert_eq!(follower.handle(&line), None);
+        assert!(!follower.is_followed(Some(400)));
+    }
+
+    #[test]
+    fn should_keep_activity_manager_in_logcat_spec() {
+        let spec = AppFollower::logcat_spec(&FilterSpec::new(crate::Level::Warn));
+        assert_eq!(spec.to_args(), ["ActivityManager:I", "*:W"]);
+
+        let spec: FilterSpec = "*:S Foo:V".parse().expect("To parse");
+        let spec = AppFollower::logcat_spec(&spec);
+        assert!(spec.is_allowed("ActivityManager", "I"));
+        assert!(spec.is_allowed("Foo", "V"));
+        assert!(!spec.is_allowed("Bar", "F"));
+
+        let spec: FilterSpec = "ActivityManager:V *:E".parse().expect("To parse");
+        assert!(AppFollower::logcat_spec(&spec).is_allowed("ActivityManager", "V"));
+    }
+}
@@ -0,0 +1,116 @@
+use std::io::{self, Write};
+
+///Writes JSON string, escaping it as necessary.
+pub fn write_str<W: Write>(out: &mut W, text: &str) -> io::Result<()> {
+    out.write_all(b"\"")?;
+
+    let mut start = 0;
+    for (idx, ch) in text.char_indices() {
+        let escape = match ch {
+            '"' => "\\\"",
+            '\\' => "\\\\",
+            '\n' => "\\n",
+            '\r' => "\\r",
+            '\t' => "\\t",
+            ch if (ch as u32) < 0x20 => {
+                out.write_all(&text.as_bytes()[start..idx])?;
+                write!(out, "\\u{:04x}", ch as u32)?;
+                start = idx + ch.len_utf8();
+                continue;
+            },
+            _ => continue,
+        };
+
+        out.write_all(&text.as_bytes()[start..idx])?;
+        out.write_all(escape.as_bytes())?;
+        start = idx + ch.len_utf8();
+    }
+
+    out.write_all(&text.as_bytes()[start..])?;
+    out.write_all(b"\"")
+}
+
+///Writes JSON string or `null` if text is empty.
+pub fn write_opt_str<W: Write>(out: &mut W, text: &str) -> io::Result<()> {
+    match text.is_empty() {
+        true => out.write_all(b"null"),
+        false => write_str(out, text),
+    }
+}
+
+///Writes JSON number or `null`.
+pub fn write_opt_num<W: Write, T: core::fmt::Display>(out: &mut W, num: Option<T>) -> io::Result<()> {
+    match num {
+        Some(num) => write!(out, "{}", num),
+        None => out.write_all(b"null"),
+    }
+}
+
+///Writes value of event, with non-finite floats as `null`.
+pub fn write_value<W: Write>(out: &mut W, value: &crate::events::Value) -> io::Result<()> {
+    use crate::events::Value;
+
+    match value {
+        Value::Int(value) => write!(out, "{}", value),
+        Value::Long(value) => write!(out, "{}", value),
+        Value::Float(value) if value.is_finite() => write!(out, "{}", value),
+        Value::Float(_) => out.write_all(b"null"),
+        Value::String(value) => write_str(out, value),
+        Value::List(values) => {
+            out.write_all(b"[")?;
+            for (idx, value) in values.iter().enumerate() {
+                if idx > 0 {
+                    out.write_all(b",")?;
+                }
+                write_value(out, value)?;
+            }
+            out.write_all(b"]")
+        },
+    }
+}
+
+///Writes fields of event as object, using position as name of unnamed field.
+pub fn write_event_fields<W: Write>(out: &mut W, event: &crate::events::Event) -> io::Result<()> {
+    out.write_all(b"{")?;
+    for (idx, (name, value)) in event.fields.iter().enumerate() {
+        if idx > 0 {
+            out.write_all(b",")?;
+        }
+        match name.is_empty() {
+            true => write!(out, "\"{}\"", idx)?,
+            false => write_str(out, name)?,
+        }
+        out.write_all(b":")?;
+        write_value(out, value)?;
+    }
+    out.write_all(b"}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{write_str, write_event_fields};
+    use crate::events::{Event, Value};
+
+    #[test]
+    fn should_escape_string() {
+        let mut out = Vec::new();
+        write_str(&mut out, "my \"super\"\\log\n\t\u{1}ё").expect("To write");
+        assert_eq!(core::str::from_utf8(&out).unwrap(), "\"my \\\"super\\\"\\\\log\\n\\t\\u0001ё\"");
+    }
+
+    #[test]
+    fn should_write_event_fields() {
+        let event = Event {
+            name: "battery_level".to_owned(),
+            fields: vec![
+                ("level".to_owned(), Value::Int(95)),
+                ("state".to_owned(), Value::String("full".to_owned())),
+                (String::new(), Value::List(vec![Value::Long(-1), Value::Float(1.5), Value::Float(f32::NAN)])),
+            ],
+        };
+
+        let mut out = Vec::new();
+        write_event_fields(&mut out, &event).expect("To write");
+        assert_eq!(core::str::from_utf8(&out).unwrap(), "{\"level\":95,\"state\":\"full\",\"2\":[-1,1.5,null]}");
+    }
+}
@@ -1,37 +1,163 @@
-#![cfg_attr(feature = "cargo-clippy", allow(clippy::style))]
+#![allow(clippy::style)]
 
 pub mod cli;
 pub mod errors;
 pub mod color;
+pub mod bugreport;
+pub mod filter;
+pub mod filterspec;
+pub mod tag;
+pub mod template;
+pub mod crash;
+pub mod symbol;
+pub mod retrace;
+pub mod follow;
+pub mod dumpsys;
+pub mod process;
+pub mod device;
+pub mod reconnect;
+pub mod adb;
+pub mod binary;
+pub mod events;
+pub mod entry;
 mod parser;
-pub use parser::{parse, LogCatLine};
+mod merge;
+mod json;
+pub use parser::{parse, LogCatLine, Format, Kind, Level};
+pub use entry::{LogEntry, LogReader};
 
-use std::collections::HashSet;
-use std::io::Write;
 use termcolor::WriteColor;
 
 const OUTPUT_SEP: &str = " ";
 //const TIME_LEN: usize = 18; //"04-16 15:39:59.337"
 const TIME_LEN: usize = 12; //"15:39:59.337"
+//pid_max of 64-bit Android is 4194304
+const PID_LEN: usize = 7;
 
-pub struct Plogcat<'a> {
+#[derive(Debug, Clone)]
+///Output mode of `Plogcat`
+pub enum Output {
+    ///Colorful human readable output, wrapped within terminal width.
+    Human,
+    ///JSON Lines, with object per line.
+    Json,
+    ///Custom template, without wrapping.
+    Template(template::Template),
+}
+
+fn level_color(level: &str) -> termcolor::ColorSpec {
+    let mut level_color = termcolor::ColorSpec::new();
+
+    match level {
+        "V" => {
+            level_color.set_fg(Some(termcolor::Color::White));
+            level_color.set_bg(Some(termcolor::Color::Black));
+        },
+        "D" => {
+            level_color.set_fg(Some(termcolor::Color::Black));
+            level_color.set_bg(Some(termcolor::Color::Blue));
+        },
+        "I" => {
+            level_color.set_fg(Some(termcolor::Color::Black));
+            level_color.set_bg(Some(termcolor::Color::Green));
+        },
+        "W" => {
+            level_color.set_fg(Some(termcolor::Color::Black));
+            level_color.set_bg(Some(termcolor::Color::Yellow));
+        },
+        "E" | "F" => {
+            level_color.set_fg(Some(termcolor::Color::Black));
+            level_color.set_bg(Some(termcolor::Color::Red));
+        },
+        _ => (),
+    }
+
+    level_color
+}
+
+///Formatter of logcat lines, writing into any color sink (e.g. locked stdout or `termcolor::Buffer`).
+pub struct Plogcat<'a, W = termcolor::StandardStreamLock<'a>> {
     buffer: String,
-    term: termcolor::StandardStreamLock<'a>,
+    term: W,
     include_time: bool,
+    include_pid: bool,
     header_size: usize,
+    //Last header of `long` format, applied to subsequent message lines.
+    long_header: String,
+    //Spans of message to highlight.
+    highlights: Vec<core::ops::Range<usize>>,
+    highlighted: bool,
     tag_colors: color::Stack,
+    java_crashes: crash::JavaDetector,
+    native_crashes: crash::NativeDetector,
+    crashes: Vec<crash::Crash>,
+    merger: merge::Merger,
     ///Max possible space to allocate for printing tag.
     pub tag_width: usize,
     ///By default automatically calculated from current console width.
     pub term_width: usize,
     ///By default none, which means include all.
-    pub tag_include: HashSet<&'a str>,
+    pub tag_include: tag::TagSet<'a>,
+    ///By default none, which means exclude none.
+    pub tag_exclude: tag::TagSet<'a>,
+    ///Pids to include, by default none, which means include all.
+    ///
+    ///Lines without pid are always included.
+    pub pids: std::collections::HashSet<u32>,
+    ///By default none, which means include all.
+    ///
+    ///Line is included if any regex matches either tag or message.
+    ///Matches within message are highlighted.
+    pub regex_include: Vec<regex::Regex>,
     ///By default none, which means exclude none.
-    pub tag_exclude: HashSet<&'a str>,
+    ///
+    ///Line is excluded if any regex matches either tag or message.
+    pub regex_exclude: Vec<regex::Regex>,
+    ///Color to highlight regex matches. By default red.
+    pub highlight_color: termcolor::Color,
+    ///By default none, which means include all.
+    pub filter: Option<filter::Filter>,
+    ///By default includes all levels of any tag.
+    pub filter_spec: filterspec::FilterSpec,
+    ///By default human readable.
+    pub output: Output,
+    ///Serial of device, by default none.
+    pub serial: Option<&'a str>,
+    ///Width of device label column, which is shown only when not zero. By default zero.
+    pub serial_width: usize,
+    ///Color of device label. By default cyan.
+    pub serial_color: termcolor::Color,
+    ///Whether to group lines of Java exceptions and native crashes. By default false.
+    pub group_crashes: bool,
+    ///Directory to write native crashes into, by default none.
+    ///
+    ///Requires `group_crashes`.
+    pub crash_dir: Option<std::path::PathBuf>,
+    ///Resolves source locations of native crash frames, by default none.
+    ///
+    ///Requires `group_crashes`.
+    pub symbolizer: Option<symbol::Symbolizer>,
+    ///ProGuard/R8 mapping to retrace messages with, by default none.
+    pub mapping: Option<retrace::Mapping>,
+    ///Application to follow across restarts, by default none.
+    ///
+    ///When set, only lines of followed processes are included.
+    pub follower: Option<follow::AppFollower>,
+    ///Offset to show time of binary entries at. By default local offset, if it can be determined.
+    pub local_offset: time::UtcOffset,
+    ///Event tags to decode events of binary logcat, by default none.
+    ///
+    ///Without tags events are shown with tag numbers and unnamed values.
+    pub event_tags: Option<events::EventTags>,
+    ///Window in milliseconds to join consecutive lines of the same message into single entry, by default none.
+    ///
+    ///Entry is printed once it is complete, which requires `flush` or `flush_pending` when no more lines are expected for a while.
+    pub merge_window: Option<u64>,
 }
 
-impl<'a> Plogcat<'a> {
-    pub fn new(term: termcolor::StandardStreamLock<'a>, tag_width: usize, include_time: bool) -> Self {
+impl<'a, W: WriteColor> Plogcat<'a, W> {
+    ///Creates new instance, with terminal width of current console.
+    pub fn new(term: W, tag_width: usize, include_time: bool, include_pid: bool) -> Self {
         let term_width = match term_size::dimensions() {
             Some((width, _)) => width,
             None => 0,
@@ -42,17 +168,46 @@ impl<'a> Plogcat<'a> {
         if include_time {
             header_size += TIME_LEN + 3 //time + brackets with space
         }
+        if include_pid {
+            header_size += PID_LEN * 2 + 2 //pid + separator + tid + space
+        }
 
         Self {
             buffer: String::new(),
             term,
             include_time,
+            include_pid,
             header_size,
+            long_header: String::new(),
+            highlights: Vec::new(),
+            highlighted: false,
             term_width,
             tag_width,
             tag_colors: color::Stack::new(),
-            tag_exclude: HashSet::new(),
-            tag_include: HashSet::new(),
+            java_crashes: crash::JavaDetector::new(),
+            native_crashes: crash::NativeDetector::new(),
+            crashes: Vec::new(),
+            merger: merge::Merger::new(),
+            tag_exclude: tag::TagSet::new(),
+            tag_include: tag::TagSet::new(),
+            pids: std::collections::HashSet::new(),
+            regex_include: Vec::new(),
+            regex_exclude: Vec::new(),
+            highlight_color: termcolor::Color::Red,
+            filter: None,
+            filter_spec: filterspec::FilterSpec::default(),
+            output: Output::Human,
+            serial: None,
+            serial_width: 0,
+            serial_color: termcolor::Color::Cyan,
+            group_crashes: false,
+            crash_dir: None,
+            symbolizer: None,
+            mapping: None,
+            follower: None,
+            local_offset: time::UtcOffset::current_local_offset().unwrap_or(time::UtcOffset::UTC),
+            event_tags: None,
+            merge_window: None,
         }
     }
 
@@ -61,63 +216,552 @@ impl<'a> Plogcat<'a> {
             return;
         }
 
-        let LogCatLine { time, level, tag, msg, .. } = match parse(&line) {
-            Some(line) => line,
-            None => return,
+        match parse(line) {
+            Some(parsed) if parsed.format.kind == Kind::Long => {
+                self.long_header.clear();
+                self.long_header.push_str(line);
+            },
+            _ if !self.long_header.is_empty() => {
+                let msg = line.trim_end();
+                if msg.is_empty() {
+                    self.long_header.clear();
+                    return;
+                }
+
+                let header = core::mem::take(&mut self.long_header);
+                if let Some(mut line) = parse(&header) {
+                    line.msg = msg;
+                    self.print_line(line);
+                }
+                self.long_header = header;
+            },
+            Some(line) => self.print_line(line),
+            None => (),
+        }
+    }
+
+    ///Handles entry of binary logcat, keeping its message intact even if it spans multiple lines.
+    pub fn handle_entry(&mut self, entry: &binary::Entry) {
+        let datetime = entry.datetime(self.local_offset);
+        let date = format!("{:02}-{:02}", u8::from(datetime.month()), datetime.day());
+        let time = format!("{:02}:{:02}:{:02}.{:03}", datetime.hour(), datetime.minute(), datetime.second(), datetime.millisecond());
+        let uid = match entry.uid {
+            Some(uid) => uid.to_string(),
+            None => String::new(),
         };
 
+        let mut format = Format::new(Kind::ThreadTime);
+        format.uid = entry.uid.is_some();
+        let text = entry.text(self.event_tags.as_ref());
+        self.print_line(LogCatLine {
+            format,
+            date: &date,
+            time: &time,
+            zone: "",
+            uid: &uid,
+            pid: Some(entry.pid),
+            tid: Some(entry.tid),
+            level: text.level,
+            tag: &text.tag,
+            msg: &text.msg,
+            buffer: entry.buffer,
+            event: text.event.as_ref(),
+        });
+    }
+
+    //Tag and level are neither changed by retrace nor by merge, so line can be dropped by them early.
+    fn is_tag_included(&self, tag: &str, level: &str) -> bool {
+        if !self.filter_spec.is_allowed(tag, level) {
+            return false;
+        }
+
         if !self.tag_exclude.is_empty() {
             if self.tag_exclude.contains(tag) {
-                return;
+                return false;
             }
         }
 
         if !self.tag_include.is_empty() {
             if !self.tag_include.contains(tag) {
-                return;
+                return false;
             }
         }
 
-        let mut tag_color = termcolor::ColorSpec::new();
-        tag_color.set_fg(Some(self.tag_colors.get_color(tag)));
+        true
+    }
 
-        let _ = self.term.set_color(&tag_color);
-        let _ = write!(&mut self.term, "{:>width$}", tag, width=self.tag_width);
+    //Applies filters of message, assuming line is already included by `is_tag_included`
+    fn is_included(&self, line: &LogCatLine<'_>) -> bool {
+        let LogCatLine { tag, msg, .. } = line;
+
+        if let Some(filter) = self.filter.as_ref() {
+            if !filter.matches(line) {
+                return false;
+            }
+        }
+
+        if !self.regex_exclude.is_empty() {
+            if self.regex_exclude.iter().any(|regex| regex.is_match(tag) || regex.is_match(msg)) {
+                return false;
+            }
+        }
+
+        if !self.regex_include.is_empty() {
+            if !self.regex_include.iter().any(|regex| regex.is_match(tag) || regex.is_match(msg)) {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    fn write_json(&mut self, line: &LogCatLine<'_>) -> std::io::Result<()> {
+        let out = &mut self.term;
+
+        out.write_all(b"{\"date\":")?;
+        json::write_opt_str(out, line.date)?;
+        out.write_all(b",\"time\":")?;
+        json::write_opt_str(out, line.time)?;
+        out.write_all(b",\"level\":")?;
+        json::write_str(out, line.level)?;
+        out.write_all(b",\"tag\":")?;
+        json::write_str(out, line.tag)?;
+        out.write_all(b",\"pid\":")?;
+        json::write_opt_num(out, line.pid)?;
+        out.write_all(b",\"tid\":")?;
+        json::write_opt_num(out, line.tid)?;
+        out.write_all(b",\"msg\":")?;
+        json::write_str(out, line.msg)?;
+        out.write_all(b",\"serial\":")?;
+        json::write_opt_str(out, self.serial.unwrap_or(""))?;
+        out.write_all(b",\"buffer\":")?;
+        json::write_opt_str(out, line.buffer.map(binary::Buffer::name).unwrap_or(""))?;
+        out.write_all(b",\"fields\":")?;
+        match line.event {
+            Some(event) => json::write_event_fields(out, event)?,
+            None => out.write_all(b"null")?,
+        }
+        out.write_all(b"}\n")
+    }
+
+    fn write_template(&mut self, line: &LogCatLine<'_>) -> std::io::Result<()> {
+        use template::{Part, Field, Align, Color};
+
+        let Self { output, term, tag_colors, buffer, serial, .. } = self;
+        let template = match output {
+            Output::Template(template) => template,
+            _ => return Ok(()),
+        };
+
+        for part in template.parts.iter() {
+            let (field, align, width, color) = match part {
+                Part::Literal(text) => {
+                    term.write_all(text.as_bytes())?;
+                    continue;
+                },
+                Part::Field { field, align, width, color } => (field, align, *width, color),
+            };
+
+            buffer.clear();
+            match field {
+                Field::Date => buffer.push_str(line.date),
+                Field::Time => buffer.push_str(line.time),
+                Field::Level => buffer.push_str(line.level),
+                Field::Tag => buffer.push_str(line.tag),
+                Field::Pid => if let Some(pid) = line.pid {
+                    buffer.push_str(&pid.to_string());
+                },
+                Field::Tid => if let Some(tid) = line.tid {
+                    buffer.push_str(&tid.to_string());
+                },
+                Field::Uid => buffer.push_str(line.uid),
+                Field::Msg => buffer.push_str(line.msg),
+                Field::Serial => buffer.push_str(serial.unwrap_or("")),
+            }
+
+            let color = match (color, field) {
+                (Some(Color::Auto), Field::Tag) => {
+                    let mut color = termcolor::ColorSpec::new();
+                    color.set_fg(Some(tag_colors.get_color(line.tag)));
+                    Some(color)
+                },
+                (Some(Color::Auto), Field::Level) => Some(level_color(line.level)),
+                (Some(Color::Fixed(fixed)), _) => {
+                    let mut color = termcolor::ColorSpec::new();
+                    color.set_fg(Some(*fixed));
+                    Some(color)
+                },
+                _ => None,
+            };
+
+            if let Some(color) = color.as_ref() {
+                term.set_color(color)?;
+            }
+            match align {
+                Align::Left => write!(term, "{:<width$}", buffer, width=width)?,
+                Align::Right => write!(term, "{:>width$}", buffer, width=width)?,
+                Align::Center => write!(term, "{:^width$}", buffer, width=width)?,
+            }
+            if color.is_some() {
+                term.reset()?;
+            }
+        }
+
+        buffer.clear();
+        term.write_all(b"\n")
+    }
+
+    fn write_event(&mut self, event: &follow::Event) {
+        if matches!(self.output, Output::Json) {
+            return;
+        }
+        self.flush();
+
+        let mut color = termcolor::ColorSpec::new();
+        color.set_bold(true);
+        let _ = match event {
+            follow::Event::Start(pid, process, reason) => {
+                color.set_fg(Some(termcolor::Color::Green));
+                let _ = self.term.set_color(&color);
+                write!(&mut self.term, ">Process {} ({}) started for {}", process, pid, reason)
+            },
+            follow::Event::Death(pid, process) => {
+                color.set_fg(Some(termcolor::Color::Red));
+                let _ = self.term.set_color(&color);
+                write!(&mut self.term, ">Process {} ({}) died", process, pid)
+            },
+        };
         let _ = self.term.reset();
+        let _ = writeln!(&mut self.term);
+    }
 
-        let _ = write!(&mut self.term, "{}", OUTPUT_SEP);
+    fn print_line(&mut self, line: LogCatLine<'_>) {
+        let event = match self.follower.as_mut() {
+            Some(follower) => follower.handle(&line),
+            None => None,
+        };
+        if let Some(event) = event.as_ref() {
+            self.write_event(event);
+        }
+        if let Some(follower) = self.follower.as_ref() {
+            if !follower.is_followed(line.pid) {
+                return;
+            }
+        }
+        if let Some(pid) = line.pid {
+            if !self.pids.is_empty() && !self.pids.contains(&pid) {
+                return;
+            }
+        }
+        if !self.is_tag_included(line.tag, line.level) {
+            return;
+        }
 
-        let mut level_color = termcolor::ColorSpec::new();
+        let retraced = match self.mapping.as_ref() {
+            //Message of binary entry is retraced line by line, keeping it single entry.
+            Some(mapping) if line.msg.contains('\n') => {
+                let mut is_retraced = false;
+                let msg = line.msg.split('\n').map(|msg| match mapping.retrace(msg) {
+                    Some(msgs) => {
+                        is_retraced = true;
+                        msgs.join("\n")
+                    },
+                    None => msg.to_owned(),
+                }).collect::<Vec<_>>();
+                match is_retraced {
+                    true => Some(vec![msg.join("\n")]),
+                    false => None,
+                }
+            },
+            Some(mapping) => mapping.retrace(line.msg),
+            None => None,
+        };
 
-        match level {
-            "V" => {
-                level_color.set_fg(Some(termcolor::Color::White));
-                level_color.set_bg(Some(termcolor::Color::Black));
+        match retraced {
+            Some(msgs) => for msg in msgs.iter() {
+                self.print_retraced(LogCatLine {
+                    msg,
+                    ..line
+                });
             },
-            "D" => {
-                level_color.set_fg(Some(termcolor::Color::Black));
-                level_color.set_bg(Some(termcolor::Color::Blue));
+            None => self.print_retraced(line),
+        }
+    }
+
+    fn print_retraced(&mut self, line: LogCatLine<'_>) {
+        let is_included = self.is_included(&line);
+
+        if self.group_crashes && is_included {
+            //Both detectors must see every line to count lines foreign to their pending crashes.
+            let is_java = self.java_crashes.push(&line);
+            let is_native = self.native_crashes.push(&line);
+            let is_buffered = is_java || is_native;
+            self.flush_crashes();
+            if is_buffered {
+                return;
+            }
+        }
+
+        match self.merge_window {
+            //Merged message is filtered as whole, so that match on any of its lines includes it entirely.
+            Some(window) => if let Some(entry) = self.merger.push(&line, window) {
+                self.write_merged(&entry);
             },
-            "I" => {
-                level_color.set_fg(Some(termcolor::Color::Black));
-                level_color.set_bg(Some(termcolor::Color::Green));
+            None => if is_included {
+                self.write_line(line);
             },
-            "W" => {
-                level_color.set_fg(Some(termcolor::Color::Black));
-                level_color.set_bg(Some(termcolor::Color::Yellow));
+        }
+    }
+
+    fn write_merged(&mut self, entry: &parser::OwnedLine) {
+        let line = entry.as_line();
+        if self.is_included(&line) {
+            self.write_line(line);
+        }
+    }
+
+    ///Prints pending entry of merged lines, if any.
+    pub fn flush(&mut self) {
+        if let Some(entry) = self.merger.take() {
+            self.write_merged(&entry);
+        }
+    }
+
+    fn write_banner(&mut self, title: &str) {
+        let width = match self.term_width {
+            0 => 80,
+            width => width,
+        };
+
+        let mut color = termcolor::ColorSpec::new();
+        color.set_fg(Some(termcolor::Color::Red)).set_bold(true);
+        let _ = self.term.set_color(&color);
+        match title.is_empty() {
+            true => {
+                let _ = write!(&mut self.term, "{:=<width$}", "", width=width);
             },
-            "E" | "F" => {
-                level_color.set_fg(Some(termcolor::Color::Black));
-                level_color.set_bg(Some(termcolor::Color::Red));
+            false => {
+                let _ = write!(&mut self.term, "{:=<width$}", format!("==== {} ", title), width=width);
             },
-            _ => (),
         }
+        let _ = self.term.reset();
+        let _ = writeln!(&mut self.term);
+    }
+
+    fn flush_crashes(&mut self) {
+        while let Some(trace) = self.java_crashes.pop_ready() {
+            self.flush();
+            let is_banner = trace.is_trace() && matches!(self.output, Output::Human);
+            if is_banner {
+                let kind = match trace.crash.fatal {
+                    true => "FATAL EXCEPTION",
+                    false => "EXCEPTION",
+                };
+                let title = match trace.crash.pid {
+                    Some(pid) => format!("{} {}({}): {}", kind, trace.crash.process, pid, trace.crash.exception),
+                    None => format!("{} {}: {}", kind, trace.crash.process, trace.crash.exception),
+                };
+                self.write_banner(&title);
+            }
+
+            for line in trace.lines.iter() {
+                self.write_line(line.as_line());
+            }
+
+            if is_banner {
+                self.write_banner("");
+            }
+
+            if trace.crash.fatal {
+                self.crashes.push(crash::Crash::Java(trace.crash));
+            }
+        }
+
+        while let Some(mut trace) = self.native_crashes.pop_ready() {
+            self.flush();
+            if let Some(symbolizer) = self.symbolizer.as_mut() {
+                trace.symbolize(symbolizer);
+            }
+
+            let is_banner = matches!(self.output, Output::Human);
+            if is_banner {
+                let title = match trace.crash.pid {
+                    Some(pid) => format!("NATIVE CRASH {}({}): {}", trace.crash.process, pid, trace.crash.signal_text()),
+                    None => format!("NATIVE CRASH {}: {}", trace.crash.process, trace.crash.signal_text()),
+                };
+                self.write_banner(&title);
+            }
+
+            for line in trace.lines.iter() {
+                self.write_line(line.as_line());
+            }
+
+            if is_banner {
+                self.write_banner("");
+            }
+
+            if let Some(dir) = self.crash_dir.as_ref() {
+                let path = dir.join(trace.file_name());
+                let result = std::fs::File::create(&path).and_then(|mut file| trace.write_text(&mut file));
+                match result {
+                    Ok(()) => eprintln!("Native crash is written to '{}'", path.display()),
+                    Err(error) => eprintln!("Failed to write native crash to '{}': {}", path.display(), error),
+                }
+            }
+
+            self.crashes.push(crash::Crash::Native(trace.crash));
+        }
+    }
+
+    ///Writes separator line, e.g. when logcat is restarted.
+    ///
+    ///Any pending output is printed before separator, and `long` header does not apply after it.
+    pub fn write_separator(&mut self, title: &str) {
+        self.flush_pending();
+        self.long_header.clear();
+        if !matches!(self.output, Output::Json) {
+            self.write_banner(title);
+        }
+    }
+
+    #[inline]
+    ///Returns underlying output.
+    pub fn get_ref(&self) -> &W {
+        &self.term
+    }
+
+    #[inline]
+    ///Returns underlying output, dropping any pending output.
+    pub fn into_inner(self) -> W {
+        self.term
+    }
+
+    ///Returns fatal crashes encountered so far.
+    pub fn crashes(&self) -> &[crash::Crash] {
+        &self.crashes
+    }
+
+    fn write_crash_origin(&mut self, date: &str, time: &str, process: &str, pid: Option<u32>) {
+        let _ = write!(&mut self.term, ">{} {}", date, time);
+        if !process.is_empty() {
+            let _ = write!(&mut self.term, " {}", process);
+        }
+        if let Some(pid) = pid {
+            let _ = write!(&mut self.term, "({})", pid);
+        }
+        let _ = write!(&mut self.term, ": ");
+    }
+
+    ///Prints all pending output, i.e. entry of merged lines and incomplete crashes.
+    ///
+    ///Should be called when no more lines are expected for a while (e.g. logcat is idle),
+    ///as subsequent lines cannot continue pending output.
+    pub fn flush_pending(&mut self) {
+        self.java_crashes.finish();
+        self.native_crashes.finish();
+        self.flush_crashes();
+        self.flush();
+    }
+
+    ///Flushes any pending output and prints summary of crashes.
+    pub fn finish(&mut self) {
+        self.flush_pending();
+
+        if self.crashes.is_empty() || matches!(self.output, Output::Json) {
+            return;
+        }
+
+        let _ = writeln!(&mut self.term, ">Crashes: {}", self.crashes.len());
+        let crashes = core::mem::take(&mut self.crashes);
+        for crash in crashes.iter() {
+            match crash {
+                crash::Crash::Java(crash) => {
+                    self.write_crash_origin(&crash.date, &crash.time, &crash.process, crash.pid);
+                    let _ = writeln!(&mut self.term, "{}", crash.exception);
+                    if !crash.cause.is_empty() {
+                        let _ = writeln!(&mut self.term, ">    Caused by: {}", crash.cause);
+                    }
+                },
+                crash::Crash::Native(crash) => {
+                    self.write_crash_origin(&crash.date, &crash.time, &crash.process, crash.pid);
+                    let _ = writeln!(&mut self.term, "{}", crash.signal_text());
+                    if !crash.abort.is_empty() {
+                        let _ = writeln!(&mut self.term, ">    Abort message: {}", crash.abort);
+                    }
+                    if let Some(frame) = crash.frames.first() {
+                        let _ = write!(&mut self.term, ">    #{:02} pc {:016x}  {}", frame.index, frame.pc, frame.library);
+                        if !frame.symbol.is_empty() {
+                            let _ = write!(&mut self.term, " ({})", frame.symbol);
+                        }
+                        let _ = writeln!(&mut self.term);
+                    }
+                },
+            }
+        }
+        self.crashes = crashes;
+    }
+
+    fn write_line(&mut self, line: LogCatLine<'_>) {
+        match self.output {
+            Output::Human => (),
+            Output::Json => {
+                let _ = self.write_json(&line);
+                return;
+            },
+            Output::Template(_) => {
+                let _ = self.write_template(&line);
+                return;
+            },
+        }
+
+        let LogCatLine { time, pid, tid, level, tag, msg, .. } = line;
+
+        if self.serial_width > 0 {
+            let mut serial_color = termcolor::ColorSpec::new();
+            serial_color.set_fg(Some(self.serial_color)).set_bold(true);
+
+            let _ = self.term.set_color(&serial_color);
+            let _ = write!(&mut self.term, "{:<width$}", self.serial.unwrap_or(""), width=self.serial_width);
+            let _ = self.term.reset();
+            let _ = write!(&mut self.term, "{}", OUTPUT_SEP);
+        }
+
+        let mut tag_color = termcolor::ColorSpec::new();
+        tag_color.set_fg(Some(self.tag_colors.get_color(tag)));
+
+        let _ = self.term.set_color(&tag_color);
+        let _ = write!(&mut self.term, "{:>width$}", tag, width=self.tag_width);
+        let _ = self.term.reset();
+
+        let _ = write!(&mut self.term, "{}", OUTPUT_SEP);
+
+        let level_color = level_color(level);
 
         if self.include_time {
             let _ = write!(&mut self.term, "[{:width$}]", time, width=TIME_LEN);
             let _ = write!(&mut self.term, "{}", OUTPUT_SEP);
         }
 
+        if self.include_pid {
+            match pid {
+                Some(pid) => {
+                    let _ = write!(&mut self.term, "{:>width$}", pid, width=PID_LEN);
+                },
+                None => {
+                    let _ = write!(&mut self.term, "{:>width$}", "", width=PID_LEN);
+                },
+            }
+            match tid {
+                Some(tid) => {
+                    let _ = write!(&mut self.term, "-{:<width$}", tid, width=PID_LEN);
+                },
+                None => {
+                    let _ = write!(&mut self.term, " {:<width$}", "", width=PID_LEN);
+                },
+            }
+            let _ = write!(&mut self.term, "{}", OUTPUT_SEP);
+        }
+
         let _ = self.term.set_color(&level_color);
         let _ = write!(&mut self.term, "{}", OUTPUT_SEP);
         let _ = write!(&mut self.term, "{}", level);
@@ -126,54 +770,300 @@ impl<'a> Plogcat<'a> {
 
         let _ = write!(&mut self.term, "{}", OUTPUT_SEP);
 
-        if self.term_width < self.header_size {
-            let _ = write!(&mut self.term, "{}\n", msg);
-        } else {
-            let wrap_area = self.term_width - self.header_size;
-            let mut msg_len = msg.chars().map(|ch| ch.len_utf8()).sum();
-            let mut msg = msg.chars();
+        self.highlights.clear();
+        for regex in self.regex_include.iter() {
+            self.highlights.extend(regex.find_iter(msg).map(|found| found.range()));
+        }
 
-            let mut last_char: Option<char> = None;
-            loop {
-                let mut consumed_len = 0;
-                let chunk_len = core::cmp::min(msg_len, wrap_area);
+        let header_size = match self.serial_width {
+            0 => self.header_size,
+            width => self.header_size + width + OUTPUT_SEP.len(),
+        };
 
-                if let Some(ch) = last_char.take() {
-                    consumed_len += ch.len_utf8();
-                    self.buffer.push(ch);
+        if self.term_width < header_size {
+            for (idx, ch) in msg.char_indices() {
+                match ch {
+                    '\n' => self.push_padding(header_size),
+                    ch => self.push_msg_char(idx, ch),
+                }
+            }
+        } else {
+            //Lines of multi-line message are wrapped separately, aligned with the first one.
+            let wrap_area = self.term_width - header_size;
+            let mut offset = 0;
+            for (idx, part) in msg.split('\n').enumerate() {
+                if idx > 0 {
+                    self.push_padding(header_size);
                 }
+                self.push_wrapped(part, offset, wrap_area, header_size);
+                offset += part.len() + 1;
+            }
+        }
 
-                while let Some(ch) = msg.next() {
-                    //Take into account that font can take up to byte len of character
-                    //so that we wouldn't overflow with fat wide characters
-                    if (consumed_len + ch.len_utf8()) <= chunk_len {
-                        self.buffer.push(ch);
-                        consumed_len += ch.len_utf8();
+        self.set_highlight(false);
+        let _ = write!(&mut self.term, "{}\n", self.buffer);
+        self.buffer.clear();
+    }
 
-                        if consumed_len == chunk_len {
-                            break;
-                        }
-                    } else {
-                        last_char = Some(ch);
-                        break;
-                    }
-                }
+    //Wraps part of message, which starts at offset.
+    fn push_wrapped(&mut self, msg: &str, offset: usize, wrap_area: usize, header_size: usize) {
+        let mut msg_len = msg.chars().map(|ch| ch.len_utf8()).sum();
+        let mut msg = msg.char_indices();
 
-                msg_len = msg_len.saturating_sub(consumed_len);
+        let mut last_char: Option<(usize, char)> = None;
+        loop {
+            let mut consumed_len = 0;
+            let chunk_len = core::cmp::min(msg_len, wrap_area);
 
-                if msg_len > 0 {
-                    self.buffer.push('\n');
-                    for _ in 0..self.header_size {
-                        self.buffer.push(' ');
+            if let Some((idx, ch)) = last_char.take() {
+                consumed_len += ch.len_utf8();
+                self.push_msg_char(offset + idx, ch);
+            }
+
+            while let Some((idx, ch)) = msg.next() {
+                //Take into account that font can take up to byte len of character
+                //so that we wouldn't overflow with fat wide characters
+                if (consumed_len + ch.len_utf8()) <= chunk_len {
+                    self.push_msg_char(offset + idx, ch);
+                    consumed_len += ch.len_utf8();
+
+                    if consumed_len == chunk_len {
+                        break;
                     }
                 } else {
+                    last_char = Some((idx, ch));
                     break;
                 }
             }
 
-            let _ = write!(&mut self.term, "{}\n", self.buffer);
-            self.buffer.clear();
+            msg_len = msg_len.saturating_sub(consumed_len);
+
+            if msg_len > 0 {
+                self.push_padding(header_size);
+            } else {
+                break;
+            }
         }
+    }
+
+    fn push_padding(&mut self, header_size: usize) {
+        //Padding should not be highlighted
+        self.set_highlight(false);
+        self.buffer.push('\n');
+        for _ in 0..header_size {
+            self.buffer.push(' ');
+        }
+    }
+
+    fn set_highlight(&mut self, highlight: bool) {
+        if self.highlighted == highlight {
+            return;
+        }
+
+        let _ = self.term.write_all(self.buffer.as_bytes());
+        self.buffer.clear();
+
+        if highlight {
+            let mut color = termcolor::ColorSpec::new();
+            color.set_fg(Some(self.highlight_color)).set_bold(true);
+            let _ = self.term.set_color(&color);
+        } else {
+            let _ = self.term.reset();
+        }
+        self.highlighted = highlight;
+    }
+
+    #[inline]
+    fn push_msg_char(&mut self, idx: usize, ch: char) {
+        let highlight = self.highlights.iter().any(|span| span.contains(&idx));
+        self.set_highlight(highlight);
+        self.buffer.push(ch);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Plogcat;
+
+    fn render(term: termcolor::Buffer, width: usize, time: bool, pid: bool, lines: &[&str]) -> String {
+        let mut plogcat = Plogcat::new(term, 8, time, pid);
+        plogcat.term_width = width;
+        for line in lines {
+            plogcat.handle_line(line);
+        }
+        plogcat.finish();
+        String::from_utf8(plogcat.into_inner().into_inner()).expect("UTF-8 output")
+    }
+
+    #[test]
+    fn should_wrap_message() {
+        let lines = [
+            "12-02 10:00:00.123  100  101 I Tag: short",
+            "12-02 10:00:00.456  100  101 W Tag: message that is long enough to be wrapped twice within width",
+        ];
+        assert_eq!(render(termcolor::Buffer::no_color(), 40, false, false, &lines), concat!(
+            "     Tag  I  short\n",
+            "     Tag  W  message that is long enough\n",
+            "              to be wrapped twice within\n",
+            "              width\n",
+        ));
+
+        //Terminal is too narrow to wrap.
+        assert_eq!(render(termcolor::Buffer::no_color(), 10, false, false, &lines[..1]), "     Tag  I  short\n");
+    }
+
+    #[test]
+    fn should_align_merged_message() {
+        let mut plogcat = Plogcat::new(termcolor::Buffer::no_color(), 8, false, false);
+        plogcat.term_width = 30;
+        plogcat.merge_window = Some(0);
+        plogcat.handle_line("12-02 10:00:00.123  100  101 I Tag: first line of message");
+        plogcat.handle_line("12-02 10:00:00.123  100  101 I Tag: second");
+        plogcat.finish();
+
+        assert_eq!(String::from_utf8(plogcat.into_inner().into_inner()).unwrap(), concat!(
+            "     Tag  I  first line of mes\n",
+            "             sage\n",
+            "             second\n",
+        ));
+    }
+
+    #[test]
+    fn should_filter_merged_message() {
+        let mut plogcat = Plogcat::new(termcolor::Buffer::no_color(), 8, false, false);
+        plogcat.merge_window = Some(0);
+        plogcat.regex_include.push(regex::Regex::new("second").unwrap());
+        plogcat.handle_line("12-02 10:00:00.123  100  101 I Tag: first");
+        plogcat.handle_line("12-02 10:00:00.123  100  101 I Tag: second");
+        plogcat.handle_line("12-02 10:00:00.123  100  101 I Tag: third");
+        plogcat.handle_line("12-02 10:00:00.124  100  101 I Tag: unrelated");
+        plogcat.finish();
+
+        assert_eq!(String::from_utf8(plogcat.into_inner().into_inner()).unwrap(), concat!(
+            "     Tag  I  first\n",
+            "             second\n",
+            "             third\n",
+        ));
+    }
+
+    #[test]
+    fn should_print_time_column() {
+        let lines = [
+            "12-02 10:00:00.123  100  101 I Tag: first",
+            "12-02 10:00:01.000  2000  2001 E Tag: second",
+            "I/Tag: without time",
+        ];
+        assert_eq!(render(termcolor::Buffer::no_color(), 0, true, true, &lines), concat!(
+            "     Tag [10:00:00.123]     100-101      I  first\n",
+            "     Tag [10:00:01.000]    2000-2001     E  second\n",
+            "     Tag [            ]                  I  without time\n",
+        ));
+
+        //Wrapped lines are aligned, even with the longest pid and tid.
+        let lines = ["12-02 10:00:00.123 4194304 4194303 I Tag: message to wrap"];
+        assert_eq!(render(termcolor::Buffer::no_color(), 52, true, true, &lines), concat!(
+            "     Tag [10:00:00.123] 4194304-4194303  I  message \n",
+            "                                            to wrap\n",
+        ));
+    }
+
+    #[test]
+    fn should_filter_by_pid() {
+        let mut plogcat = Plogcat::new(termcolor::Buffer::no_color(), 8, false, false);
+        plogcat.pids.insert(100);
+        plogcat.handle_line("12-02 10:00:00.123  100  101 I Tag: included");
+        plogcat.handle_line("12-02 10:00:00.123  200  201 I Tag: excluded");
+        plogcat.handle_line("I/Tag: without pid");
+
+        assert_eq!(String::from_utf8(plogcat.into_inner().into_inner()).unwrap(), concat!(
+            "     Tag  I  included\n",
+            "     Tag  I  without pid\n",
+        ));
+    }
+
+    #[test]
+    fn should_flush_before_separator() {
+        let mut plogcat = Plogcat::new(termcolor::Buffer::no_color(), 8, false, false);
+        plogcat.term_width = 20;
+        plogcat.merge_window = Some(0);
+        plogcat.handle_line("12-02 10:00:00.123  100  101 I Tag: merged");
+        plogcat.handle_line("[ 12-02 10:00:00.124   100:  101 W/Tag ]");
+        plogcat.write_separator("NEXT");
+        plogcat.handle_line("not a message of long header");
+        plogcat.finish();
+
+        assert_eq!(String::from_utf8(plogcat.into_inner().into_inner()).unwrap(), concat!(
+            "     Tag  I  merged\n",
+            "==== NEXT ==========\n",
+        ));
+    }
+
+    #[test]
+    fn should_flush_pending_crash() {
+        let mut plogcat = Plogcat::new(termcolor::Buffer::no_color(), 10, false, false);
+        plogcat.group_crashes = true;
+        plogcat.handle_line("12-02 10:00:00.123  666  667 W System.err: java.io.IOException: closed");
+        plogcat.handle_line("12-02 10:00:00.123  666  667 W System.err: \tat com.foo.Bar.baz(Bar.kt:12)");
+        assert!(plogcat.get_ref().as_slice().is_empty());
+
+        plogcat.term_width = 40;
+        plogcat.flush_pending();
+        assert_eq!(String::from_utf8(plogcat.into_inner().into_inner()).unwrap(), concat!(
+            "==== EXCEPTION (666): java.io.IOException: closed \n",
+            "System.err  W  java.io.IOException: clos\n",
+            "               ed\n",
+            "System.err  W  \tat com.foo.Bar.baz(Bar.k\n",
+            "               t:12)\n",
+            "========================================\n",
+        ));
+    }
+
+    #[test]
+    fn should_follow_app_with_level_filter() {
+        let mut plogcat = Plogcat::new(termcolor::Buffer::no_color(), 8, false, false);
+        plogcat.follower = Some(super::follow::AppFollower::new("com.foo".to_owned(), false, Vec::new()));
+        plogcat.filter_spec = super::filterspec::FilterSpec::new(super::Level::Warn);
+        plogcat.handle_line("12-02 10:00:00.100  555  600 I ActivityManager: Start proc 300:com.foo/u0a1 for activity {com.foo/.Main}");
+        plogcat.handle_line("12-02 10:00:00.200  300  300 I Tag: info");
+        plogcat.handle_line("12-02 10:00:00.300  300  300 W Tag: warning");
+        plogcat.handle_line("12-02 10:00:00.400  400  400 W Tag: other app");
+
+        assert_eq!(String::from_utf8(plogcat.into_inner().into_inner()).unwrap(), concat!(
+            ">Process com.foo (300) started for activity {com.foo/.Main}\n",
+            "     Tag  W  warning\n",
+        ));
+    }
+
+    #[test]
+    fn should_keep_binary_entry_intact() {
+        let mut plogcat = Plogcat::new(termcolor::Buffer::no_color(), 8, false, false);
+        plogcat.output = super::Output::Json;
+        plogcat.local_offset = time::UtcOffset::UTC;
+        plogcat.handle_entry(&super::binary::Entry {
+            pid: 100,
+            tid: 101,
+            sec: 1_600_000_000,
+            nsec: 0,
+            buffer: Some(super::binary::Buffer::Main),
+            uid: None,
+            payload: b"\x04Tag\0line1\nline2\0".to_vec(),
+        });
+
+        assert_eq!(String::from_utf8(plogcat.into_inner().into_inner()).unwrap(), concat!(
+            "{\"date\":\"09-13\",\"time\":\"12:26:40.000\",\"level\":\"I\",\"tag\":\"Tag\",\"pid\":100,\"tid\":101,",
+            "\"msg\":\"line1\\nline2\",\"serial\":null,\"buffer\":\"main\",\"fields\":null}\n",
+        ));
+    }
 
+    #[test]
+    fn should_print_colors() {
+        let lines = [
+            "12-02 10:00:00.123  100  101 I Tag: info",
+            "12-02 10:00:00.456  100  101 E Other: error",
+        ];
+        assert_eq!(render(termcolor::Buffer::ansi(), 0, false, false, &lines), concat!(
+            "\x1b[0m\x1b[32m     Tag\x1b[0m \x1b[0m\x1b[30m\x1b[42m I \x1b[0m info\n",
+            "\x1b[0m\x1b[33m   Other\x1b[0m \x1b[0m\x1b[30m\x1b[41m E \x1b[0m error\n",
+        ));
     }
 }
@@ -1,15 +1,219 @@
-#![cfg_attr(feature = "cargo-clippy", allow(clippy::style))]
+#![allow(clippy::style)]
 
 use std::io::BufRead;
+use core::convert::TryFrom;
+use core::sync::atomic::{AtomicBool, Ordering};
 
 pub use plogcat::*;
 
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+//Interrupt is intercepted only when there is summary to print on exit.
+//
+//Reading of input is restarted after interrupt, so it may block until next line.
+//Hence second interrupt terminates as usual, without summary.
+fn handle_interrupt() {
+    let result = unsafe {
+        signal_hook::low_level::register(signal_hook::consts::SIGINT, || if INTERRUPTED.swap(true, Ordering::Relaxed) {
+            let _ = signal_hook::low_level::emulate_default_handler(signal_hook::consts::SIGINT);
+        })
+    };
+
+    if let Err(error) = result {
+        eprintln!("Failed to set interrupt handler: {}", error);
+    }
+}
+
 fn main() {
     let code = run();
     std::process::exit(code as _);
 }
 
+fn create_plogcat<'a>(args: &'a cli::Cli, term: &'a termcolor::StandardStream, local_offset: time::UtcOffset) -> Result<Plogcat<'a>, isize> {
+    let mut plogcat = Plogcat::new(term.lock(), args.tag_width, args.time, args.show_pid);
+    plogcat.local_offset = local_offset;
+
+    if let Some(filter) = args.filter.as_ref() {
+        match filter.parse() {
+            Ok(filter) => plogcat.filter = Some(filter),
+            Err(error) => {
+                eprintln!("Invalid filter: {}", error);
+                return Err(1);
+            }
+        }
+    }
+
+    if let Some(format) = args.format.as_ref() {
+        match format.parse() {
+            Ok(template) => plogcat.output = Output::Template(template),
+            Err(error) => {
+                eprintln!("Invalid format: {}", error);
+                return Err(1);
+            }
+        }
+    }
+
+    if let Some(mapping) = args.mapping.as_ref() {
+        match std::fs::read_to_string(mapping) {
+            Ok(mapping) => plogcat.mapping = Some(retrace::Mapping::parse(&mapping)),
+            Err(error) => {
+                eprintln!("Failed to read mapping '{}': {}", mapping, error);
+                return Err(errors::IO_FAIL);
+            }
+        }
+    }
+
+    if let Some(event_tags) = args.event_tags.as_ref() {
+        match std::fs::read_to_string(event_tags) {
+            Ok(event_tags) => plogcat.event_tags = Some(events::EventTags::parse(&event_tags)),
+            Err(error) => {
+                eprintln!("Failed to read event tags '{}': {}", event_tags, error);
+                return Err(errors::IO_FAIL);
+            }
+        }
+    }
+
+    plogcat.merge_window = args.merge;
+    plogcat.filter_spec = args.get_filter_spec();
+    plogcat.group_crashes = args.is_crashes();
+    plogcat.crash_dir = args.crash_dir.as_ref().map(std::path::PathBuf::from);
+    plogcat.symbolizer = args.symbols.as_ref().map(|dir| symbol::Symbolizer::new(dir.into(), args.addr2line.clone()));
+    if args.json {
+        plogcat.output = Output::Json;
+    }
+    plogcat.tag_exclude = args.ignored_tag.iter().collect();
+    plogcat.tag_include = args.tag.iter().collect();
+    plogcat.regex_include = args.regex.clone();
+    plogcat.regex_exclude = args.exclude_regex.clone();
+    if let Some(color) = args.highlight {
+        plogcat.highlight_color = color;
+    }
+    Ok(plogcat)
+}
+
+fn read_lines<R: BufRead>(mut input: R, plogcat: &mut Plogcat<'_>) -> std::io::Result<()> {
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        if input.read_until(b'\n', &mut line)? == 0 || INTERRUPTED.load(Ordering::Relaxed) {
+            break Ok(());
+        }
+
+        //Saved logs can contain garbage, so do not give up on whole file
+        plogcat.handle_line(&String::from_utf8_lossy(&line));
+    }
+}
+
+fn read_entries<R: std::io::Read>(mut input: R, plogcat: &mut Plogcat<'_>) -> std::io::Result<()> {
+    while !INTERRUPTED.load(Ordering::Relaxed) {
+        match binary::read_entry(&mut input)? {
+            Some(entry) => plogcat.handle_entry(&entry),
+            None => break,
+        }
+    }
+    Ok(())
+}
+
+#[inline]
+fn read_input<R: BufRead>(args: &cli::Cli, input: R, plogcat: &mut Plogcat<'_>) -> std::io::Result<()> {
+    match args.binary {
+        true => read_entries(input, plogcat),
+        false => read_lines(input, plogcat),
+    }
+}
+
+//Applies app filter to saved logs, where it cannot be resolved using device.
+fn set_offline_app(args: &cli::Cli, plogcat: &mut Plogcat<'_>) -> Result<(), isize> {
+    if args.time_limit.is_some() || args.max_count.is_some() || args.current {
+        eprintln!("--time-limit, --max-count and --current require device and cannot be used with saved logs");
+        return Err(1);
+    }
+
+    match args.app.as_ref() {
+        Some(cli::App::Pid(pid)) => match u32::try_from(*pid) {
+            Ok(pid) => {
+                plogcat.pids.insert(pid);
+            },
+            Err(_) => {
+                eprintln!("Invalid pid {}", pid);
+                return Err(1);
+            }
+        },
+        //Only processes started within logs can be found.
+        Some(cli::App::PackageName(name)) => plogcat.follower = Some(follow::AppFollower::new(name.clone(), args.sub_processes, Vec::new())),
+        None => (),
+    }
+    Ok(())
+}
+
+fn run_files(args: &cli::Cli, term: &termcolor::StandardStream, local_offset: time::UtcOffset) -> isize {
+    let mut plogcat = match create_plogcat(args, term, local_offset) {
+        Ok(plogcat) => plogcat,
+        Err(error) => return error,
+    };
+    if let Err(error) = set_offline_app(args, &mut plogcat) {
+        return error;
+    }
+
+    for file in args.file.iter() {
+        let result = if file == "-" {
+            read_input(args, std::io::stdin().lock(), &mut plogcat)
+        } else {
+            match std::fs::File::open(file) {
+                Ok(input) => read_input(args, std::io::BufReader::new(input), &mut plogcat),
+                Err(error) => {
+                    eprintln!("Failed to open '{}': {}", file, error);
+                    return errors::IO_FAIL;
+                }
+            }
+        };
+
+        if let Err(error) = result {
+            eprintln!("Failed to read '{}': {}", file, error);
+            return errors::IO_FAIL;
+        }
+    }
+
+    plogcat.finish();
+    0
+}
+
+fn run_bugreport(args: &cli::Cli, term: &termcolor::StandardStream, path: &str, local_offset: time::UtcOffset) -> isize {
+    let mut plogcat = match create_plogcat(args, term, local_offset) {
+        Ok(plogcat) => plogcat,
+        Err(error) => return error,
+    };
+    if let Err(error) = set_offline_app(args, &mut plogcat) {
+        return error;
+    }
+    let mut current = None;
+
+    let result = bugreport::read_file(path, |section, line| {
+        if !args.section.is_empty() && !args.section.contains(&section) {
+            return;
+        }
+
+        if current != Some(section) {
+            current = Some(section);
+            plogcat.write_separator(section.title());
+        }
+
+        plogcat.handle_line(line);
+    });
+    plogcat.finish();
+
+    match result {
+        Ok(()) => 0,
+        Err(error) => {
+            eprintln!("Failed to read bugreport '{}': {}", path, error);
+            errors::IO_FAIL
+        }
+    }
+}
+
 fn run() -> isize {
+    //Offset cannot be determined once there are multiple threads.
+    let local_offset = time::UtcOffset::current_local_offset().unwrap_or(time::UtcOffset::UTC);
     let args = std::env::args().skip(1).collect::<Vec<_>>();
     let mut args = match cli::new(args.iter().map(String::as_str)) {
         Ok(args) => args,
@@ -20,99 +224,353 @@ fn run() -> isize {
         args.tag_width = 23;
     }
 
-    let mut adb = args.get_logcat_cmd();
+    let color_choice = match args.machine || args.json {
+        false => termcolor::ColorChoice::Auto,
+        true => termcolor::ColorChoice::Never,
+    };
+    let term = termcolor::StandardStream::stdout(color_choice);
+
+    if args.is_crashes() {
+        handle_interrupt();
+    }
+
+    if let Some(bugreport) = args.bugreport.as_ref() {
+        return run_bugreport(&args, &term, bugreport, local_offset);
+    }
 
-    if args.app.is_none() && args.current {
-        println!(">Pid not specified, find currently run app");
-        if let Err(error) = args.set_current_app() {
-            return error
-        }
+    if !args.file.is_empty() {
+        return run_files(&args, &term, local_offset);
     }
 
-    match args.get_app_pid() {
-        Ok(pids) => {
-            for pid in pids {
-                println!(">Filtering by pid {}", pid);
-                adb.arg(&format!("--pid={}", pid));
+    let serials = match args.get_serials() {
+        Ok(serials) => serials,
+        Err(error) => return error,
+    };
+    let serial_width = match serials.len() {
+        1 => 0,
+        _ => serials.iter().map(|serial| serial.as_ref().map(|serial| serial.len()).unwrap_or(0)).max().unwrap_or(0),
+    };
+    //Logcat that exits by itself is not restarted.
+    let is_reconnect = args.reconnect && !args.dump && args.max_count.is_none();
+    if is_reconnect && !args.binary && !reconnect::is_resumable(args.logcat_format.iter().map(String::as_str)) {
+        eprintln!("Logcat format has no time on every line, so lines can be duplicated after reconnect");
+    }
+
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let mut devices = scope_guard::scope_guard!(|devices| {
+        for device in devices {
+            if let Some(mut adb) = device.adb {
+                if let Err(error) = adb.kill() {
+                    eprintln!("Failed to kill adb: {}", error);
+                }
             }
-        },
-        Err(err) => return err,
+        }
+    }, Vec::<Device>::new());
+    let mut plogcats = Vec::new();
+
+    for (idx, serial) in serials.iter().enumerate() {
+        let serial = serial.as_deref();
+        let label = match serial_width {
+            0 => String::new(),
+            _ => format!("{}: ", serial.unwrap_or("")),
+        };
+
+        let (pids, follower) = match resolve_app(&args, serial, &label) {
+            Ok(result) => result,
+            Err(error) => return error,
+        };
+
+        if args.clear {
+            if let Err(error) = args.clear_logcat(serial) {
+                return error;
+            }
+        }
+
+        let adb = match spawn_logcat(args.get_logcat_args(), &args, serial, &pids, follower.is_some(), idx, &sender) {
+            Ok(adb) => adb,
+            Err(error) => return error,
+        };
+        devices.push(Device {
+            serial,
+            label,
+            pids,
+            adb: Some(adb),
+            resume: reconnect::Resume::new(),
+        });
+
+        let mut plogcat = match create_plogcat(&args, &term, local_offset) {
+            Ok(plogcat) => plogcat,
+            Err(error) => return error,
+        };
+        plogcat.serial = serial;
+        plogcat.serial_width = serial_width;
+        plogcat.serial_color = color::device_color(idx);
+        plogcat.follower = follower;
+        if plogcat.event_tags.is_none() && args.is_events() {
+            plogcat.event_tags = args.get_event_tags(serial);
+        }
+        plogcats.push(plogcat);
     }
 
-    adb.stdout(std::process::Stdio::piped());
-    adb.arg("-v");
-    adb.arg("time");
+    let mut running = plogcats.len();
+    let mut result = 0;
+    while running > 0 && !INTERRUPTED.load(Ordering::Relaxed) {
+        let message = match receiver.recv_timeout(core::time::Duration::from_millis(100)) {
+            Ok(message) => message,
+            Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {
+                //No more lines of merged message or crash are expected, once logcat is idle.
+                for plogcat in plogcats.iter_mut() {
+                    plogcat.flush_pending();
+                }
+                continue;
+            },
+            Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => break,
+        };
+
+        match message {
+            Message::Line(idx, line) => if devices[idx].resume.accept(&line) {
+                plogcats[idx].handle_line(&line);
+            },
+            Message::Entry(idx, entry) => if devices[idx].resume.accept_entry(&entry) {
+                plogcats[idx].handle_entry(&entry);
+            },
+            Message::Closed(idx) => {
+                let device = &mut devices[idx];
+                let is_success = match device.adb.take().map(Logcat::wait) {
+                    Some(Ok(is_success)) => is_success,
+                    Some(Err(error)) => {
+                        eprintln!("Cannot poll adb process: {}", error);
+                        false
+                    },
+                    None => true,
+                };
+
+                if is_reconnect {
+                    eprintln!(">{}Logcat is closed, waiting for device", device.label);
+                    wait_for_device(&args, device.serial, idx, &sender);
+                } else {
+                    running -= 1;
+                    if !is_success {
+                        result = errors::ADB_FAIL;
+                    }
+                }
+            },
+            Message::Connected(idx) => {
+                let device = &mut devices[idx];
+                let logcat_args = match device.resume.restart() {
+                    Some(timestamp) => args.get_logcat_args_since(Some(timestamp)),
+                    None => args.get_logcat_args(),
+                };
+                match spawn_logcat(logcat_args, &args, device.serial, &device.pids, plogcats[idx].follower.is_some(), idx, &sender) {
+                    Ok(adb) => {
+                        device.adb = Some(adb);
+                        plogcats[idx].write_separator(&match device.serial {
+                            Some(serial) => format!("RECONNECTED {}", serial),
+                            None => "RECONNECTED".to_owned(),
+                        });
+                    },
+                    Err(error) => {
+                        running -= 1;
+                        result = error;
+                    }
+                }
+            },
+        }
+    }
 
-    if args.clear {
-        let mut adb = args.get_logcat_cmd();
-        adb.arg("-c");
+    for plogcat in plogcats.iter_mut() {
+        plogcat.finish();
+    }
+    result
+}
+
+//Logcat stream of single device.
+struct Device<'a> {
+    serial: Option<&'a str>,
+    //Prefix of messages, to distinguish devices.
+    label: String,
+    //Pids to filter by on logcat side.
+    pids: Vec<u64>,
+    adb: Option<Logcat>,
+    resume: reconnect::Resume,
+}
 
+//Running logcat, either streamed by adb server or by adb child process.
+enum Logcat {
+    Stream(std::net::TcpStream),
+    Child(std::process::Child),
+}
 
-        if let Err(error) = adb.status() {
-            eprintln!("Failed to execute adb -c: {}", error);
-            return errors::ADB_FAIL
+impl Logcat {
+    fn kill(&mut self) -> std::io::Result<()> {
+        match self {
+            Logcat::Stream(stream) => stream.shutdown(std::net::Shutdown::Both),
+            Logcat::Child(child) => child.kill(),
         }
     }
 
-    let color_choice = match args.machine {
-        false => termcolor::ColorChoice::Auto,
-        true => termcolor::ColorChoice::Never,
-    };
-    let term = termcolor::StandardStream::stdout(color_choice);
+    //Returns whether logcat is successful, which is assumed for stream as it has no exit code.
+    fn wait(self) -> std::io::Result<bool> {
+        match self {
+            Logcat::Stream(_) => Ok(true),
+            Logcat::Child(mut child) => child.wait().map(|status| status.success()),
+        }
+    }
+}
+
+enum Message {
+    Line(usize, String),
+    Entry(usize, binary::Entry),
+    //Logcat of device is closed.
+    Closed(usize),
+    //Device is connected again.
+    Connected(usize),
+}
+
+//Resolves app of device, returning pids to filter by logcat or follower of app, if app is followed by package.
+//
+//Status is printed to stderr, so that it is not mixed with output (e.g. JSON lines).
+fn resolve_app(args: &cli::Cli, serial: Option<&str>, label: &str) -> Result<(Vec<u64>, Option<follow::AppFollower>), isize> {
+    let mut app = args.app.clone();
+    if app.is_none() && args.current {
+        eprintln!(">{}Pid not specified, find currently run app", label);
+        app = args.get_current_app(serial)?;
+        if app.is_none() {
+            eprintln!(">{}No app currently running", label);
+        }
+    }
+
+    //Package is followed across restarts by client, so that new processes are not missed.
+    match app.as_ref() {
+        Some(cli::App::PackageName(name)) => {
+            let pids = args.find_app_pids(serial, name)?;
+
+            if pids.is_empty() {
+                eprintln!(">{}Waiting for {} to start", label, name);
+            }
+            for pid in pids.iter() {
+                eprintln!(">{}Following pid {}", label, pid);
+            }
+            Ok((Vec::new(), Some(follow::AppFollower::new(name.clone(), args.sub_processes, pids.into_iter().map(|pid| pid as u32)))))
+        },
+        _ => {
+            let pids = args.get_app_pid(serial, app.as_ref())?;
+            for pid in pids.iter() {
+                eprintln!(">{}Filtering by pid {}", label, pid);
+            }
+            Ok((pids, None))
+        },
+    }
+}
 
-    if let Some(filter) = args.get_filter_spec() {
-        adb.arg(&filter);
+//Starts logcat with arguments, returning it with its output.
+//
+//Talks to adb server directly, falling back to adb binary (e.g. when server is not running yet).
+fn start_logcat(logcat_args: &[String], args: &cli::Cli, serial: Option<&str>) -> Result<(Logcat, Box<dyn std::io::Read + Send>), isize> {
+    let command = core::iter::once("logcat").chain(logcat_args.iter().map(String::as_str)).map(adb::quote).collect::<Vec<_>>().join(" ");
+    if let Ok(stream) = adb::Client::default().stream(&args.get_transport(serial), &command) {
+        match stream.try_clone() {
+            Ok(output) => return Ok((Logcat::Stream(stream), Box::new(output))),
+            Err(error) => eprintln!("Failed to read logcat stream: {}", error),
+        }
     }
 
-    let adb = match adb.spawn() {
+    let mut adb = args.get_adb_cmd(serial);
+    adb.arg("logcat");
+    adb.args(logcat_args);
+    adb.stdout(std::process::Stdio::piped());
+    let mut adb = match adb.spawn() {
         Ok(adb) => adb,
         Err(error) => {
             eprintln!("Failed to start adb: {}", error);
-            return errors::ADB_FAIL;
+            return Err(errors::ADB_FAIL);
         }
     };
 
-    let mut adb = scope_guard::scope_guard!(|mut adb| {
-        if let Err(error) = adb.kill() {
-            eprintln!("Failed to kill adb: {}", error);
-        }
-    }, adb);
-
-    let stdout = match adb.stdout.take() {
-        Some(stdout) => stdout,
+    match adb.stdout.take() {
+        Some(stdout) => Ok((Logcat::Child(adb), Box::new(stdout))),
         None => {
             eprintln!("Stdout pipe is not available");
-            return 0;
+            let _ = adb.kill();
+            let _ = adb.wait();
+            Err(errors::ADB_FAIL)
         }
-    };
-    let mut stdout = std::io::BufReader::new(stdout);
+    }
+}
 
-    let mut plogcat = Plogcat::new(term.lock(), args.tag_width, args.time);
-    plogcat.tag_exclude = args.ignored_tag.iter().map(String::as_ref).collect();
-    plogcat.tag_include = args.tag.iter().map(String::as_ref).collect();
+//Starts logcat, sending its lines as messages of device `idx`
+//
+//When app is followed, logcat must keep lines of its starts and deaths, which are filtered by `Plogcat` instead.
+fn spawn_logcat(mut logcat_args: Vec<String>, args: &cli::Cli, serial: Option<&str>, pids: &[u64], is_following: bool, idx: usize, sender: &std::sync::mpsc::Sender<Message>) -> Result<Logcat, isize> {
+    for pid in pids {
+        logcat_args.push(format!("--pid={}", pid));
+    }
 
-    let mut line = String::new();
-    loop {
-        match adb.try_wait() {
-            Ok(Some(status)) => {
-                adb.into_inner();
-                break match status.success() {
-                    true => 0,
-                    false => errors::ADB_FAIL,
-                };
-            },
-            Ok(None) => (),
-            Err(error) => {
-                eprintln!("Cannot poll adb process: {}", error);
-                break errors::ADB_FAIL;
+    let mut filter_spec = args.get_filter_spec();
+    if is_following {
+        filter_spec = follow::AppFollower::logcat_spec(&filter_spec);
+    }
+    if !filter_spec.is_empty() {
+        logcat_args.extend(filter_spec.to_args());
+    }
+
+    let (adb, stdout) = start_logcat(&logcat_args, args, serial)?;
+    let sender = sender.clone();
+    let is_binary = args.binary;
+    std::thread::spawn(move || {
+        let mut stdout = std::io::BufReader::new(stdout);
+        let mut line = Vec::new();
+        loop {
+            let message = match is_binary {
+                true => binary::read_entry(&mut stdout).map(|entry| entry.map(|entry| Message::Entry(idx, entry))),
+                false => {
+                    line.clear();
+                    stdout.read_until(b'\n', &mut line).map(|size| match size {
+                        0 => None,
+                        _ => Some(Message::Line(idx, String::from_utf8_lossy(&line).into_owned())),
+                    })
+                },
+            };
+
+            match message {
+                Ok(Some(message)) => if sender.send(message).is_err() {
+                    return;
+                },
+                Ok(None) => break,
+                Err(error) => {
+                    eprintln!("Failed to read={}", error);
+                    break;
+                }
             }
         }
+        let _ = sender.send(Message::Closed(idx));
+    });
 
-        line.clear();
-        if let Err(error) = stdout.read_line(&mut line) {
-            eprintln!("Failed to read={}", error);
-        }
+    Ok(adb)
+}
 
-        plogcat.handle_line(&line);
-    }
+//Waits for device in background, notifying when it is connected.
+fn wait_for_device(args: &cli::Cli, serial: Option<&str>, idx: usize, sender: &std::sync::mpsc::Sender<Message>) {
+    const RETRY_INTERVAL: core::time::Duration = core::time::Duration::from_secs(1);
+
+    let transport = args.get_transport(serial);
+    let mut adb = args.get_adb_cmd(serial);
+    adb.arg("wait-for-device");
+    let sender = sender.clone();
+    std::thread::spawn(move || loop {
+        //Give device a moment, in case logcat is closed for other reason.
+        std::thread::sleep(RETRY_INTERVAL);
+        let status = match adb::Client::default().wait_for_device(&transport) {
+            Ok(()) => Ok(true),
+            Err(_) => adb.status().map(|status| status.success()),
+        };
+        match status {
+            Ok(true) => {
+                let _ = sender.send(Message::Connected(idx));
+                break;
+            },
+            Ok(false) => (),
+            Err(error) => eprintln!("Failed to run adb wait-for-device: {}", error),
+        }
+    });
 }
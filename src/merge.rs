@@ -0,0 +1,104 @@
+use crate::LogCatLine;
+use crate::parser::OwnedLine;
+
+//Returns time in milliseconds, either since midnight (`HH:MM:SS.mmm`) or since Epoch/boot (`SSSS.mmm`).
+fn time_ms(time: &str) -> Option<u64> {
+    let (time, fraction) = match time.find('.') {
+        Some(idx) => (&time[..idx], &time[idx + 1..]),
+        None => (time, ""),
+    };
+
+    let mut seconds = 0u64;
+    for part in time.split(':') {
+        seconds = seconds.checked_mul(60)?.checked_add(part.parse().ok()?)?;
+    }
+
+    let mut ms = 0;
+    for idx in 0..3 {
+        ms = ms * 10 + match fraction.as_bytes().get(idx) {
+            Some(digit) if digit.is_ascii_digit() => u64::from(digit - b'0'),
+            Some(_) => return None,
+            None => 0,
+        };
+    }
+
+    seconds.checked_mul(1000)?.checked_add(ms)
+}
+
+fn is_continuation(entry: &LogCatLine<'_>, line: &LogCatLine<'_>, window: u64) -> bool {
+    if entry.tag != line.tag || entry.level != line.level || entry.pid != line.pid || entry.tid != line.tid || entry.date != line.date || entry.buffer != line.buffer {
+        return false;
+    }
+
+    match (time_ms(entry.time), time_ms(line.time)) {
+        (Some(start), Some(time)) => start <= time && time - start <= window,
+        _ => false,
+    }
+}
+
+///Joins consecutive lines of the same message, i.e. lines of the same tag, level, pid and tid within time window.
+pub(crate) struct Merger {
+    entry: Option<OwnedLine>,
+}
+
+impl Merger {
+    pub(crate) fn new() -> Self {
+        Self {
+            entry: None,
+        }
+    }
+
+    ///Pushes line, returning previous entry if line does not continue it.
+    ///
+    ///Window is in milliseconds since first line of entry.
+    pub(crate) fn push(&mut self, line: &LogCatLine<'_>, window: u64) -> Option<OwnedLine> {
+        if let Some(entry) = self.entry.as_mut() {
+            if is_continuation(&entry.as_line(), line, window) {
+                entry.push_msg(line.msg);
+                return None;
+            }
+        }
+
+        self.entry.replace(OwnedLine::new(line))
+    }
+
+    #[inline]
+    ///Takes pending entry.
+    pub(crate) fn take(&mut self) -> Option<OwnedLine> {
+        self.entry.take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{time_ms, Merger};
+    use crate::parse;
+
+    #[test]
+    fn should_parse_time() {
+        assert_eq!(time_ms("01:02:03.456"), Some(3_723_456));
+        assert_eq!(time_ms("01:02:03.456789"), Some(3_723_456));
+        assert_eq!(time_ms("1600000000.5"), Some(1_600_000_000_500));
+        assert_eq!(time_ms("12:00:00"), Some(43_200_000));
+        assert_eq!(time_ms("12:0a:00"), None);
+    }
+
+    #[test]
+    fn should_merge_lines() {
+        let mut merger = Merger::new();
+        assert!(merger.push(&parse("12-02 10:00:00.000  100  101 I Tag: first").unwrap(), 5).is_none());
+        assert!(merger.push(&parse("12-02 10:00:00.000  100  101 I Tag: second").unwrap(), 5).is_none());
+        assert!(merger.push(&parse("12-02 10:00:00.005  100  101 I Tag: third").unwrap(), 5).is_none());
+
+        let entry = merger.push(&parse("12-02 10:00:00.006  100  101 I Tag: fourth").unwrap(), 5).expect("To have entry");
+        assert_eq!(entry.as_line().msg, "first\nsecond\nthird");
+
+        let entry = merger.push(&parse("12-02 10:00:00.006  100  102 I Tag: fifth").unwrap(), 5).expect("To have entry");
+        assert_eq!(entry.as_line().msg, "fourth");
+        let entry = merger.push(&parse("12-02 10:00:00.006  100  102 W Tag: sixth").unwrap(), 5).expect("To have entry");
+        assert_eq!(entry.as_line().msg, "fifth");
+
+        assert_eq!(merger.take().expect("To have entry").as_line().msg, "sixth");
+        assert!(merger.take().is_none());
+    }
+}
@@ -1,10 +1,140 @@
+///Base logcat output format, as specified by `-v` option.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    ///`I/tag(  pid): msg`
+    Brief,
+    ///`I(  pid) msg  (tag)`
+    Process,
+    ///`I/tag: msg`
+    Tag,
+    ///`I(  pid:  tid) msg`
+    Thread,
+    ///`date time I/tag(  pid): msg`
+    Time,
+    ///`date time  pid  tid I tag: msg`
+    ThreadTime,
+    ///`[ date time  pid:  tid I/tag ]` header, followed by message lines and empty line.
+    Long,
+}
+
+///Logcat output format, detected from the line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Format {
+    pub kind: Kind,
+    ///Date includes year.
+    pub year: bool,
+    ///Time is followed by time zone.
+    pub zone: bool,
+    ///Time is in seconds since Epoch.
+    pub epoch: bool,
+    ///Time is in seconds since boot.
+    pub monotonic: bool,
+    ///Line includes UID (or its name).
+    pub uid: bool,
+    ///Time is in microseconds precision.
+    pub usec: bool,
+    ///Time is in nanoseconds precision.
+    pub nsec: bool,
+}
+
+impl Format {
+    #[inline]
+    pub(crate) const fn new(kind: Kind) -> Self {
+        Self {
+            kind,
+            year: false,
+            zone: false,
+            epoch: false,
+            monotonic: false,
+            uid: false,
+            usec: false,
+            nsec: false,
+        }
+    }
+}
+
 ///Logcat line
 pub struct LogCatLine<'a> {
+    pub format: Format,
+    ///Empty if format has no date (i.e. no time, epoch or monotonic time)
     pub date: &'a str,
+    ///Empty if format has no time.
     pub time: &'a str,
+    ///Empty unless `zone` modifier is used.
+    pub zone: &'a str,
+    ///Empty unless `uid` modifier is used.
+    pub uid: &'a str,
+    ///Process id, if present in format.
+    pub pid: Option<u32>,
+    ///Thread id, if present in format (i.e. `thread`, `threadtime` and `long`).
+    pub tid: Option<u32>,
     pub level: &'a str,
     pub tag: &'a str,
+    ///Always empty for `long` format header.
     pub msg: &'a str,
+    ///Source buffer, known only for binary logcat output.
+    pub buffer: Option<crate::binary::Buffer>,
+    ///Decoded event, known only for binary logcat output of events buffer.
+    pub event: Option<&'a crate::events::Event>,
+}
+
+#[derive(Debug, Clone)]
+///Owned copy of `LogCatLine`, used to buffer lines.
+pub(crate) struct OwnedLine {
+    format: Format,
+    date: String,
+    time: String,
+    zone: String,
+    uid: String,
+    pid: Option<u32>,
+    tid: Option<u32>,
+    level: String,
+    tag: String,
+    msg: String,
+    buffer: Option<crate::binary::Buffer>,
+    event: Option<crate::events::Event>,
+}
+
+impl OwnedLine {
+    pub(crate) fn new(line: &LogCatLine<'_>) -> Self {
+        Self {
+            format: line.format,
+            date: line.date.to_owned(),
+            time: line.time.to_owned(),
+            zone: line.zone.to_owned(),
+            uid: line.uid.to_owned(),
+            pid: line.pid,
+            tid: line.tid,
+            level: line.level.to_owned(),
+            tag: line.tag.to_owned(),
+            msg: line.msg.to_owned(),
+            buffer: line.buffer,
+            event: line.event.cloned(),
+        }
+    }
+
+    ///Appends line to message.
+    pub(crate) fn push_msg(&mut self, msg: &str) {
+        self.msg.push('\n');
+        self.msg.push_str(msg);
+    }
+
+    pub(crate) fn as_line(&self) -> LogCatLine<'_> {
+        LogCatLine {
+            format: self.format,
+            date: &self.date,
+            time: &self.time,
+            zone: &self.zone,
+            uid: &self.uid,
+            pid: self.pid,
+            tid: self.tid,
+            level: &self.level,
+            tag: &self.tag,
+            msg: &self.msg,
+            buffer: self.buffer,
+            event: self.event.as_ref(),
+        }
+    }
 }
 
 macro_rules! next_part {
@@ -27,68 +157,368 @@ macro_rules! next_part {
     }
 }
 
-//^([0-9]+-[0-9]+\s[0-9]+:[0-9]+:[0-9]+.[0-9]+)\s([A-Z])/(.+?)\( *(\d+)\): (.*?)$;
-///Parses line from output of logcat -v time
-pub fn parse(text: &str) -> Option<LogCatLine<'_>> {
-    let mut cursor = text;
-    let date = next_part!(cursor);
-    let time = next_part!(cursor);
-    let level_tag = if let Some(idx) = cursor.find('(') {
-            let res = &cursor[..idx];
-            cursor = &cursor[idx..];
-
-            if let Some(msg_idx) = cursor.find(':') {
-                if let Some(msg) = cursor.get(msg_idx+2..) {
-                    cursor = msg;
-                } else {
-                    return None;
+#[inline(always)]
+fn is_digits(text: &str) -> bool {
+    !text.is_empty() && text.bytes().all(|byte| byte.is_ascii_digit())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+///Android log level, ordered by priority.
+pub enum Level {
+    Verbose,
+    Debug,
+    Info,
+    Warn,
+    Error,
+    ///Also known as assert.
+    Fatal,
+    Silent,
+}
+
+impl Level {
+    ///Parses single letter level, as printed by logcat.
+    pub fn from_letter(level: &str) -> Option<Self> {
+        match level.as_bytes() {
+            [b'v'] | [b'V'] => Some(Level::Verbose),
+            [b'd'] | [b'D'] => Some(Level::Debug),
+            [b'i'] | [b'I'] => Some(Level::Info),
+            [b'w'] | [b'W'] => Some(Level::Warn),
+            [b'e'] | [b'E'] => Some(Level::Error),
+            [b'f'] | [b'F'] | [b'a'] | [b'A'] => Some(Level::Fatal),
+            [b's'] | [b'S'] => Some(Level::Silent),
+            _ => None,
+        }
+    }
+
+    ///Returns single letter level, as printed by logcat.
+    pub fn letter(self) -> &'static str {
+        match self {
+            Level::Verbose => "V",
+            Level::Debug => "D",
+            Level::Info => "I",
+            Level::Warn => "W",
+            Level::Error => "E",
+            Level::Fatal => "F",
+            Level::Silent => "S",
+        }
+    }
+}
+
+#[inline(always)]
+fn is_level(text: &str) -> bool {
+    text.len() == 1 && text.as_bytes()[0].is_ascii_alphabetic()
+}
+
+//Process identifiers are either `pid`, `uid:pid`, `pid:tid` or `uid:pid:tid`
+fn split_ids(text: &str) -> Option<([&str; 3], usize)> {
+    let mut ids = [""; 3];
+    let mut len = 0;
+
+    for part in text.split(|ch: char| ch == ':' || ch == ' ').filter(|part| !part.is_empty()) {
+        if len == ids.len() || !part.bytes().all(|byte| byte.is_ascii_alphanumeric() || byte == b'_') {
+            return None;
+        }
+
+        ids[len] = part;
+        len += 1;
+    }
+
+    if len == 0 || !is_digits(ids[len - 1]) {
+        return None;
+    }
+
+    Some((ids, len))
+}
+
+//Returns date, time, zone and remaining text.
+fn parse_timestamp<'a>(text: &'a str, format: &mut Format) -> Option<(&'a str, &'a str, &'a str, &'a str)> {
+    let mut cursor = text.trim_start();
+
+    let first = next_part!(cursor);
+    let (date, time) = if first.contains('-') {
+        let time = next_part!(cursor);
+        if !time.contains(':') || !first.as_bytes()[0].is_ascii_digit() {
+            return None;
+        }
+
+        format.year = first.split('-').count() == 3;
+        (first, time)
+    } else {
+        let mut parts = first.splitn(2, '.');
+        let seconds = parts.next().unwrap_or(first);
+        if !is_digits(seconds) || !parts.next().map(is_digits).unwrap_or(true) {
+            return None;
+        }
+
+        //Epoch cannot be represented by less than 10 digits since 2001
+        if seconds.len() >= 10 {
+            format.epoch = true;
+        } else {
+            format.monotonic = true;
+        }
+        ("", first)
+    };
+
+    match time.rsplit('.').next().map(str::len) {
+        Some(6) if time.contains('.') => format.usec = true,
+        Some(9) if time.contains('.') => format.nsec = true,
+        _ => (),
+    }
+
+    let mut zone = "";
+    if let Some(idx) = cursor.find(' ') {
+        let candidate = &cursor[..idx];
+        if candidate.len() == 5 && (candidate.starts_with('+') || candidate.starts_with('-')) && is_digits(&candidate[1..]) {
+            format.zone = true;
+            zone = candidate;
+            cursor = cursor[idx..].trim_start();
+        }
+    }
+
+    Some((date, time, zone, cursor))
+}
+
+//Parses `tag(ids): msg` returning tag, ids and msg
+fn split_tag_ids(text: &str) -> Option<(&str, &str, &str)> {
+    let mut offset = 0;
+
+    while let Some(idx) = text[offset..].find('(') {
+        let start = offset + idx;
+        if let Some(end) = text[start..].find(')') {
+            let end = start + end;
+            let ids = &text[start + 1..end];
+            if let Some(msg) = text[end + 1..].strip_prefix(':') {
+                if split_ids(ids).is_some() {
+                    return Some((text[..start].trim_end(), ids, msg.strip_prefix(' ').unwrap_or(msg)));
                 }
-            } else {
-                return None;
             }
+        }
+
+        offset = start + 1;
+    }
 
-            res.trim()
+    None
+}
+
+//Parses `tag: msg`
+fn split_tag(text: &str) -> Option<(&str, &str)> {
+    if let Some(idx) = text.find(": ") {
+        Some((text[..idx].trim_end(), &text[idx + 2..]))
+    } else if let Some(tag) = text.strip_suffix(':') {
+        Some((tag.trim_end(), ""))
     } else {
-        return None;
-    };
+        None
+    }
+}
 
-    let (level, tag) = {
-        let mut level_tag_split = level_tag.splitn(2, '/');
-        let level = match level_tag_split.next() {
-            Some(level) => level,
-            None => unsafe {
-                core::hint::unreachable_unchecked()
-            },
-        };
+//[ date time  pid:  tid I/tag ]
+fn parse_long(header: &str) -> Option<LogCatLine<'_>> {
+    let mut format = Format::new(Kind::Long);
+    let (date, time, zone, cursor) = parse_timestamp(header, &mut format)?;
 
-        let tag = match level_tag_split.next() {
-            Some(tag) => tag,
-            None => return None,
-        };
+    //Level is single ASCII letter, so anything else before `/` is not header.
+    let level_idx = match cursor.find('/') {
+        Some(idx) if idx > 0 && cursor.is_char_boundary(idx - 1) => idx - 1,
+        _ => return None,
+    };
+    let level = &cursor[level_idx..level_idx + 1];
+    if !is_level(level) {
+        return None;
+    }
 
-        (level, tag)
+    let (ids, ids_len) = split_ids(&cursor[..level_idx])?;
+    let uid = match ids_len {
+        2 => "",
+        3 => ids[0],
+        _ => return None,
     };
+    format.uid = !uid.is_empty();
 
     Some(LogCatLine {
+        format,
         date,
         time,
+        zone,
+        uid,
+        pid: ids[ids_len - 2].parse().ok(),
+        tid: ids[ids_len - 1].parse().ok(),
         level,
-        tag,
-        msg: cursor.trim_end(),
+        tag: cursor[level_idx + 2..].trim_end(),
+        msg: "",
+        buffer: None,
+        event: None,
     })
 }
 
+//^([0-9]+-[0-9]+\s[0-9]+:[0-9]+:[0-9]+.[0-9]+)\s([A-Z])/(.+?)\( *(\d+)\): (.*?)$;
+///Parses line from output of logcat, detecting format used by `-v` option.
+///
+///`long` format is split into header line, that has empty message, and message lines, that
+///cannot be parsed on their own.
+pub fn parse(text: &str) -> Option<LogCatLine<'_>> {
+    let text = text.trim_end();
+
+    if let Some(header) = text.strip_prefix("[ ").and_then(|header| header.strip_suffix(']')) {
+        return parse_long(header);
+    }
+
+    let mut format = Format::new(Kind::Brief);
+    let (date, time, zone, mut cursor) = match parse_timestamp(text, &mut format) {
+        Some(result) => result,
+        None => {
+            format = Format::new(Kind::Brief);
+            ("", "", "", text)
+        }
+    };
+    let has_time = !time.is_empty();
+
+    let level = match cursor.as_bytes().get(1) {
+        Some(b'/') | Some(b'(') if is_level(&cursor[..1]) => &cursor[..1],
+        _ if has_time => {
+            //date time [uid] pid tid level tag: msg
+            format.kind = Kind::ThreadTime;
+            let first = next_part!(cursor);
+            let second = next_part!(cursor);
+            let third = next_part!(cursor);
+            let (uid, pid, tid, level) = if is_level(third) {
+                ("", first, second, third)
+            } else {
+                let level = next_part!(cursor);
+                (first, second, third, level)
+            };
+
+            if !is_digits(pid) || !is_digits(tid) || !is_level(level) {
+                return None;
+            }
+            format.uid = !uid.is_empty();
+
+            let (tag, msg) = split_tag(cursor)?;
+            return Some(LogCatLine {
+                format,
+                date,
+                time,
+                zone,
+                uid,
+                pid: pid.parse().ok(),
+                tid: tid.parse().ok(),
+                level,
+                tag,
+                msg,
+                buffer: None,
+                event: None,
+            });
+        },
+        _ => return None,
+    };
+
+    let body = &cursor[2..];
+    match cursor.as_bytes()[1] {
+        b'/' => match split_tag_ids(body) {
+            Some((tag, ids, msg)) => {
+                format.kind = if has_time { Kind::Time } else { Kind::Brief };
+                let (ids, ids_len) = split_ids(ids)?;
+                let uid = match ids_len {
+                    1 => "",
+                    2 => ids[0],
+                    _ => return None,
+                };
+                format.uid = !uid.is_empty();
+
+                Some(LogCatLine {
+                    format,
+                    date,
+                    time,
+                    zone,
+                    uid,
+                    pid: ids[ids_len - 1].parse().ok(),
+                    tid: None,
+                    level,
+                    tag,
+                    msg,
+                    buffer: None,
+                    event: None,
+                })
+            },
+            None if !has_time => {
+                format.kind = Kind::Tag;
+                let (tag, msg) = split_tag(body)?;
+
+                Some(LogCatLine {
+                    format,
+                    date,
+                    time,
+                    zone,
+                    uid: "",
+                    pid: None,
+                    tid: None,
+                    level,
+                    tag,
+                    msg,
+                    buffer: None,
+                    event: None,
+                })
+            },
+            None => None,
+        },
+        b'(' if !has_time => {
+            let end = body.find(')')?;
+            let (ids, ids_len) = split_ids(&body[..end])?;
+            let msg = body[end + 1..].strip_prefix(' ').unwrap_or(&body[end + 1..]);
+
+            let process_tag = match msg.strip_suffix(')').and_then(|msg| msg.rfind("  (").map(|idx| (msg, idx))) {
+                Some((msg, idx)) if ids_len < 3 => Some((&msg[idx + 3..], &msg[..idx])),
+                _ => None,
+            };
+
+            let (tag, msg, uid, pid, tid) = match process_tag {
+                Some((tag, msg)) => {
+                    format.kind = Kind::Process;
+                    (tag, msg, if ids_len == 2 { ids[0] } else { "" }, ids[ids_len - 1], "")
+                },
+                None if ids_len > 1 => {
+                    format.kind = Kind::Thread;
+                    ("", msg, if ids_len == 3 { ids[0] } else { "" }, ids[ids_len - 2], ids[ids_len - 1])
+                },
+                None => {
+                    format.kind = Kind::Process;
+                    ("", msg, "", ids[0], "")
+                },
+            };
+            format.uid = !uid.is_empty();
+
+            Some(LogCatLine {
+                format,
+                date,
+                time,
+                zone,
+                uid,
+                pid: pid.parse().ok(),
+                tid: tid.parse().ok(),
+                level,
+                tag,
+                msg,
+                buffer: None,
+                event: None,
+            })
+        },
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::parse;
+    use super::{parse, Kind};
 
     #[test]
     fn should_parse_valid_line() {
         let result = parse("12-02    24:01:13.237   i/flutter ( 666):     my super log ").expect("To parse");
+        assert_eq!(result.format.kind, Kind::Time);
         assert_eq!(result.date, "12-02");
         assert_eq!(result.time, "24:01:13.237");
         assert_eq!(result.level, "i");
         assert_eq!(result.tag, "flutter");
+        assert_eq!(result.pid, Some(666));
+        assert_eq!(result.tid, None);
         assert_eq!(result.msg, "    my super log");
 
         let result = parse("12-02    24:01:13.237   i/flutter ( 666): my super log ").expect("To parse");
@@ -99,4 +529,110 @@ mod tests {
         assert_eq!(result.msg, "my super log");
     }
 
+    #[test]
+    fn should_parse_formats_without_time() {
+        let result = parse("I/flutter ( 666): my super log").expect("To parse brief");
+        assert_eq!(result.format.kind, Kind::Brief);
+        assert_eq!(result.time, "");
+        assert_eq!(result.level, "I");
+        assert_eq!(result.tag, "flutter");
+        assert_eq!(result.msg, "my super log");
+
+        let result = parse("I/flutter (u0_a12:  666): my (super) log").expect("To parse brief with uid");
+        assert_eq!(result.format.kind, Kind::Brief);
+        assert!(result.format.uid);
+        assert_eq!(result.uid, "u0_a12");
+        assert_eq!(result.pid, Some(666));
+        assert_eq!(result.tag, "flutter");
+        assert_eq!(result.msg, "my (super) log");
+
+        let result = parse("W/ActivityManager: my super: log").expect("To parse tag");
+        assert_eq!(result.format.kind, Kind::Tag);
+        assert_eq!(result.pid, None);
+        assert_eq!(result.level, "W");
+        assert_eq!(result.tag, "ActivityManager");
+        assert_eq!(result.msg, "my super: log");
+
+        let result = parse("D(  666) my super log  (flutter)").expect("To parse process");
+        assert_eq!(result.format.kind, Kind::Process);
+        assert_eq!(result.level, "D");
+        assert_eq!(result.tag, "flutter");
+        assert_eq!(result.pid, Some(666));
+        assert_eq!(result.msg, "my super log");
+
+        let result = parse("E(  666:  667) my super log").expect("To parse thread");
+        assert_eq!(result.format.kind, Kind::Thread);
+        assert_eq!(result.pid, Some(666));
+        assert_eq!(result.tid, Some(667));
+        assert_eq!(result.level, "E");
+        assert_eq!(result.tag, "");
+        assert_eq!(result.msg, "my super log");
+    }
+
+    #[test]
+    fn should_parse_threadtime() {
+        let result = parse("12-02 24:01:13.237   666   667 I flutter : my super log\n").expect("To parse");
+        assert_eq!(result.format.kind, Kind::ThreadTime);
+        assert!(!result.format.uid);
+        assert_eq!(result.pid, Some(666));
+        assert_eq!(result.tid, Some(667));
+        assert_eq!(result.date, "12-02");
+        assert_eq!(result.time, "24:01:13.237");
+        assert_eq!(result.level, "I");
+        assert_eq!(result.tag, "flutter");
+        assert_eq!(result.msg, "my super log");
+
+        let result = parse("2021-12-02 24:01:13.237123 +0100 10123   666   667 W chatty  : my super log").expect("To parse with modifiers");
+        assert_eq!(result.format.kind, Kind::ThreadTime);
+        assert!(result.format.year);
+        assert!(result.format.zone);
+        assert!(result.format.usec);
+        assert!(result.format.uid);
+        assert_eq!(result.date, "2021-12-02");
+        assert_eq!(result.time, "24:01:13.237123");
+        assert_eq!(result.zone, "+0100");
+        assert_eq!(result.uid, "10123");
+        assert_eq!(result.pid, Some(666));
+        assert_eq!(result.tid, Some(667));
+        assert_eq!(result.level, "W");
+        assert_eq!(result.tag, "chatty");
+        assert_eq!(result.msg, "my super log");
+
+        let result = parse("         1638403273.237123456   666   667 I flutter : log").expect("To parse epoch");
+        assert!(result.format.epoch);
+        assert!(result.format.nsec);
+        assert_eq!(result.date, "");
+        assert_eq!(result.time, "1638403273.237123456");
+
+        let result = parse("  1234.237   666   667 I flutter : log").expect("To parse monotonic");
+        assert!(result.format.monotonic);
+        assert_eq!(result.time, "1234.237");
+        assert_eq!(result.tag, "flutter");
+    }
+
+    #[test]
+    fn should_parse_long_header() {
+        let result = parse("[ 12-02 24:01:13.237   666:  667 I/flutter  ]\n").expect("To parse");
+        assert_eq!(result.format.kind, Kind::Long);
+        assert_eq!(result.date, "12-02");
+        assert_eq!(result.time, "24:01:13.237");
+        assert_eq!(result.level, "I");
+        assert_eq!(result.tag, "flutter");
+        assert_eq!(result.pid, Some(666));
+        assert_eq!(result.tid, Some(667));
+        assert_eq!(result.msg, "");
+
+        let result = parse("[ 12-02 24:01:13.237 10123:  666:  667 I/flutter  ]").expect("To parse with uid");
+        assert_eq!(result.uid, "10123");
+        assert_eq!(result.pid, Some(666));
+        assert_eq!(result.tid, Some(667));
+    }
+
+    #[test]
+    fn should_not_parse_garbage() {
+        assert!(parse("--------- beginning of main").is_none());
+        assert!(parse("").is_none());
+        assert!(parse("my super log").is_none());
+        assert!(parse("[ 12-02 10:00:00.000  1:  2 é/tag ]").is_none());
+    }
 }
@@ -0,0 +1,83 @@
+#[derive(Debug, Clone, PartialEq, Eq)]
+///Row of `ps` output.
+pub struct Process {
+    pub pid: u32,
+    pub name: String,
+}
+
+///Returns whether process belongs to package.
+///
+///Process of package is named after package, while its sub-processes are named `package:process`.
+pub fn is_app_process(name: &str, package: &str, sub_processes: bool) -> bool {
+    match name.strip_prefix(package) {
+        Some("") => true,
+        Some(rest) => sub_processes && rest.starts_with(':'),
+        None => false,
+    }
+}
+
+///Parses output of `ps`, using header to locate `PID` column.
+///
+///Name is expected as last column, which is the case for `ps -A -o PID,NAME` as well as for `ps` of old devices (i.e. `USER PID PPID VSIZE RSS WCHAN PC NAME`).
+pub fn parse_ps(output: &str) -> Vec<Process> {
+    let mut lines = output.lines();
+    let pid_idx = match lines.next().and_then(|header| header.split_whitespace().position(|column| column == "PID")) {
+        Some(idx) => idx,
+        None => return Vec::new(),
+    };
+
+    lines.filter_map(|line| {
+        let mut columns = line.split_whitespace();
+        let pid = columns.nth(pid_idx)?.parse().ok()?;
+        let name = columns.last()?;
+        Some(Process {
+            pid,
+            name: name.to_owned(),
+        })
+    }).collect()
+}
+
+///Parses output of `pidof`, returning none if it is not list of pids.
+pub fn parse_pidof(output: &str) -> Option<Vec<u32>> {
+    output.split_whitespace().map(|pid| pid.parse().ok()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_ps, parse_pidof, is_app_process, Process};
+
+    #[test]
+    fn should_parse_ps() {
+        let output = "  PID NAME\n    1 init\n 1200 com.foo\n 1201 com.foo:service\n 1300 com.foo.debug\n";
+        assert_eq!(parse_ps(output), [
+            Process { pid: 1, name: "init".to_owned() },
+            Process { pid: 1200, name: "com.foo".to_owned() },
+            Process { pid: 1201, name: "com.foo:service".to_owned() },
+            Process { pid: 1300, name: "com.foo.debug".to_owned() },
+        ]);
+
+        let output = "USER     PID   PPID  VSIZE  RSS     WCHAN    PC        NAME\nroot      1     0     8904   788   ffffffff 00000000 S /init\nu0_a85    1200  180   1234   5678  ffffffff 00000000 S com.foo\n";
+        assert_eq!(parse_ps(output), [
+            Process { pid: 1, name: "/init".to_owned() },
+            Process { pid: 1200, name: "com.foo".to_owned() },
+        ]);
+
+        assert!(parse_ps("bad argument: -A").is_empty());
+    }
+
+    #[test]
+    fn should_match_app_process() {
+        assert!(is_app_process("com.foo", "com.foo", false));
+        assert!(!is_app_process("com.foo:service", "com.foo", false));
+        assert!(is_app_process("com.foo:service", "com.foo", true));
+        assert!(!is_app_process("com.foo.debug", "com.foo", true));
+        assert!(!is_app_process("com.foobar:service", "com.foo", true));
+    }
+
+    #[test]
+    fn should_parse_pidof() {
+        assert_eq!(parse_pidof("1200 1201\n"), Some(vec![1200, 1201]));
+        assert_eq!(parse_pidof(""), Some(Vec::new()));
+        assert_eq!(parse_pidof("/system/bin/sh: pidof: not found"), None);
+    }
+}
@@ -0,0 +1,157 @@
+use crate::parse;
+use crate::binary::Entry;
+
+///Returns whether logcat with specified `-v` formats can be resumed since last line.
+///
+///It requires wall clock time on every line, which is missing in formats without time, `monotonic`
+///and message lines of `long` format. Binary logcat can always be resumed.
+pub fn is_resumable<'a, I: IntoIterator<Item = &'a str>>(formats: I) -> bool {
+    formats.into_iter().flat_map(|format| format.split(',')).all(|format| match format.trim() {
+        "brief" | "long" | "process" | "raw" | "tag" | "thread" | "monotonic" => false,
+        _ => true,
+    })
+}
+
+///Tracks last timestamp of logcat, in order to resume it after reconnect without losing or duplicating lines.
+pub struct Resume {
+    timestamp: String,
+    //Lines with last timestamp, which are printed again when logcat is resumed since this timestamp.
+    lines: Vec<String>,
+    skipping: bool,
+    replayed: usize,
+}
+
+impl Resume {
+    pub fn new() -> Self {
+        Self {
+            timestamp: String::new(),
+            lines: Vec::new(),
+            skipping: false,
+            replayed: 0,
+        }
+    }
+
+    ///Returns timestamp of line in format accepted by `logcat -T`
+    fn line_timestamp(line: &str) -> Option<String> {
+        let line = parse(line)?;
+        if line.format.monotonic || line.time.is_empty() {
+            None
+        } else if line.date.is_empty() {
+            match line.format.epoch {
+                true => Some(line.time.to_owned()),
+                false => None,
+            }
+        } else {
+            Some(format!("{} {}", line.date, line.time))
+        }
+    }
+
+    ///Handles line, returning whether it should be printed.
+    pub fn accept(&mut self, line: &str) -> bool {
+        match Self::line_timestamp(line) {
+            Some(timestamp) => self.accept_at(timestamp, line),
+            None => true,
+        }
+    }
+
+    ///Handles entry of binary logcat, returning whether it should be printed.
+    pub fn accept_entry(&mut self, entry: &Entry) -> bool {
+        let line = format!("{} {} {}", entry.pid, entry.tid, String::from_utf8_lossy(&entry.payload));
+        self.accept_at(entry.timestamp(), &line)
+    }
+
+    fn accept_at(&mut self, timestamp: String, line: &str) -> bool {
+        if timestamp != self.timestamp {
+            self.timestamp = timestamp;
+            self.lines.clear();
+            self.skipping = false;
+        } else if self.skipping {
+            if self.lines.get(self.replayed).map(|last| last == line).unwrap_or(false) {
+                self.replayed += 1;
+                return false;
+            }
+            self.skipping = false;
+        }
+
+        self.lines.push(line.to_owned());
+        true
+    }
+
+    ///Prepares to resume, returning timestamp to resume since, if any line is seen.
+    ///
+    ///Without timestamp logcat should be restarted with its original time limit.
+    pub fn restart(&mut self) -> Option<&str> {
+        self.skipping = true;
+        self.replayed = 0;
+
+        match self.timestamp.is_empty() {
+            true => None,
+            false => Some(&self.timestamp),
+        }
+    }
+}
+
+impl Default for Resume {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_resumable, Resume};
+    use crate::binary::Entry;
+
+    #[test]
+    fn should_detect_resumable_formats() {
+        assert!(is_resumable(None));
+        assert!(is_resumable(vec!["time", "uid", "year"]));
+        assert!(is_resumable(vec!["threadtime,epoch"]));
+        assert!(!is_resumable(vec!["long"]));
+        assert!(!is_resumable(vec!["threadtime", "monotonic"]));
+        assert!(!is_resumable(vec!["brief"]));
+    }
+
+    #[test]
+    fn should_skip_replayed_lines() {
+        let mut resume = Resume::new();
+        assert_eq!(resume.restart(), None);
+        assert!(resume.accept("12-02 24:01:13.237  100  100 I Tag: first"));
+        assert!(resume.accept("12-02 24:01:14.000  100  100 I Tag: second"));
+        assert!(resume.accept("12-02 24:01:14.000  100  100 I Tag: third"));
+
+        assert_eq!(resume.restart(), Some("12-02 24:01:14.000"));
+        assert!(!resume.accept("12-02 24:01:14.000  100  100 I Tag: second"));
+        assert!(!resume.accept("12-02 24:01:14.000  100  100 I Tag: third"));
+        assert!(resume.accept("12-02 24:01:14.000  100  100 I Tag: fourth"));
+        assert!(resume.accept("12-02 24:01:15.000  100  100 I Tag: fifth"));
+
+        assert_eq!(resume.restart(), Some("12-02 24:01:15.000"));
+        assert!(resume.accept("12-02 24:01:16.000  100  100 I Tag: sixth"));
+        assert!(resume.accept("garbage"));
+    }
+
+    #[test]
+    fn should_skip_replayed_entries() {
+        let entry = |nsec: u32, msg: &str| Entry {
+            pid: 100,
+            tid: 100,
+            sec: 1_600_000_000,
+            nsec,
+            buffer: None,
+            uid: None,
+            payload: format!("\x04Tag\0{}\0", msg).into_bytes(),
+        };
+
+        let mut resume = Resume::new();
+        assert!(resume.accept_entry(&entry(5, "first")));
+        assert!(resume.accept_entry(&entry(5, "second")));
+
+        assert_eq!(resume.restart(), Some("1600000000.000000005"));
+        assert!(!resume.accept_entry(&entry(5, "first")));
+        assert!(!resume.accept_entry(&entry(5, "second")));
+        assert!(resume.accept_entry(&entry(5, "third")));
+        assert!(resume.accept_entry(&entry(6, "fourth")));
+    }
+}
@@ -0,0 +1,297 @@
+use std::collections::HashMap;
+
+//`at a.b.c(Unknown Source:12)`
+const FRAME_REGEX: &str = r"^(\s*at )([A-Za-z_$][\w$]*(?:\.[A-Za-z_$][\w$]*)*)\.([\w$<>]+)\(([^:)]*)(?::(\d+))?\)(.*)$";
+const CLASS_REGEX: &str = r"[A-Za-z_$][\w$]*(?:\.[A-Za-z_$][\w$]*)+";
+const SOURCE_FILE: &str = "\"fileName\":\"";
+
+///Mapping of obfuscated method, i.e. `[start:end:]type name(args)[:original_start[:original_end]] -> obfuscated`
+struct Method {
+    //Range of obfuscated lines, if specified.
+    range: Option<(u32, u32)>,
+    //Range of original lines, if specified.
+    original_range: Option<(u32, u32)>,
+    //Can be qualified with class name for inlined methods.
+    name: String,
+}
+
+impl Method {
+    fn contains(&self, line: u32) -> bool {
+        match self.range {
+            Some((start, end)) => start <= line && line <= end,
+            None => true,
+        }
+    }
+
+    fn original_line(&self, line: u32) -> u32 {
+        match (self.range, self.original_range) {
+            (Some((start, end)), Some((original_start, original_end))) => match end.saturating_sub(start) == original_end.saturating_sub(original_start) {
+                true => original_start + line.saturating_sub(start),
+                false => original_start,
+            },
+            (None, Some((original_start, _))) => original_start,
+            _ => line,
+        }
+    }
+}
+
+struct Class {
+    name: String,
+    methods: HashMap<String, Vec<Method>>,
+}
+
+#[inline]
+fn parse_range(text: &str) -> Option<(u32, u32)> {
+    let mut parts = text.split(':');
+    let start = parts.next()?.trim().parse().ok()?;
+    let end = match parts.next() {
+        Some(end) => end.trim().parse().ok()?,
+        None => start,
+    };
+    Some((start, end))
+}
+
+fn parse_method(text: &str) -> Option<(String, Method)> {
+    let idx = text.find(" -> ")?;
+    let obfuscated = text[idx + 4..].trim();
+    let mut member = text[..idx].trim();
+
+    let mut range = None;
+    if member.starts_with(|ch: char| ch.is_ascii_digit()) {
+        //Range is followed by return type, which starts after second colon.
+        let mut colons = member.match_indices(':').map(|(idx, _)| idx);
+        let _ = colons.next()?;
+        let second = colons.next()?;
+        range = parse_range(&member[..second]);
+        member = &member[second + 1..];
+    }
+
+    let args_start = member.find('(')?;
+    let args_end = member.rfind(')')?;
+    let name = member[..args_start].rsplit(' ').next()?;
+    let original_range = member[args_end + 1..].strip_prefix(':').and_then(parse_range);
+
+    Some((obfuscated.to_owned(), Method {
+        range,
+        original_range,
+        name: name.to_owned(),
+    }))
+}
+
+///ProGuard/R8 mapping, used to retrace obfuscated class names and stack frames.
+pub struct Mapping {
+    //Obfuscated class to its original.
+    classes: HashMap<String, Class>,
+    //Original class to its source file.
+    source_files: HashMap<String, String>,
+    frame: regex::Regex,
+    class: regex::Regex,
+}
+
+impl Mapping {
+    ///Parses content of `mapping.txt`, ignoring unrecognized lines.
+    pub fn parse(text: &str) -> Self {
+        let mut classes = HashMap::new();
+        let mut source_files = HashMap::new();
+        let mut current: Option<(String, Class)> = None;
+
+        for line in text.lines() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() {
+                continue;
+            }
+
+            if let Some(comment) = trimmed.strip_prefix('#') {
+                //R8 metadata of class, e.g. `# {"id":"sourceFile","fileName":"Foo.kt"}`
+                if let (Some((_, class)), Some(idx)) = (current.as_ref(), comment.find(SOURCE_FILE)) {
+                    let file = &comment[idx + SOURCE_FILE.len()..];
+                    if let Some(end) = file.find('"') {
+                        source_files.insert(class.name.clone(), file[..end].to_owned());
+                    }
+                }
+            } else if !line.starts_with(char::is_whitespace) {
+                if let Some((obfuscated, class)) = current.take() {
+                    classes.insert(obfuscated, class);
+                }
+
+                let class = match trimmed.strip_suffix(':') {
+                    Some(class) => class,
+                    None => continue,
+                };
+                if let Some(idx) = class.find(" -> ") {
+                    current = Some((class[idx + 4..].trim().to_owned(), Class {
+                        name: class[..idx].trim().to_owned(),
+                        methods: HashMap::new(),
+                    }));
+                }
+            } else if let Some((_, class)) = current.as_mut() {
+                if let Some((obfuscated, method)) = parse_method(trimmed) {
+                    class.methods.entry(obfuscated).or_insert_with(Vec::new).push(method);
+                }
+            }
+        }
+
+        if let Some((obfuscated, class)) = current.take() {
+            classes.insert(obfuscated, class);
+        }
+
+        Self {
+            classes,
+            source_files,
+            frame: regex::Regex::new(FRAME_REGEX).expect("valid frame regex"),
+            class: regex::Regex::new(CLASS_REGEX).expect("valid class regex"),
+        }
+    }
+
+    #[inline]
+    ///Returns whether mapping has no classes.
+    pub fn is_empty(&self) -> bool {
+        self.classes.is_empty()
+    }
+
+    #[inline]
+    ///Returns original name of obfuscated class, if known.
+    pub fn class(&self, obfuscated: &str) -> Option<&str> {
+        self.classes.get(obfuscated).map(|class| class.name.as_str())
+    }
+
+    fn source_file(&self, class: &str) -> String {
+        if let Some(file) = self.source_files.get(class) {
+            return file.clone();
+        }
+
+        let name = class.rsplit('.').next().unwrap_or(class);
+        let name = name.split('$').next().unwrap_or(name);
+        format!("{}.java", name)
+    }
+
+    fn retrace_frame(&self, msg: &str) -> Option<Vec<String>> {
+        let captures = self.frame.captures(msg)?;
+        let class = self.classes.get(&captures[2])?;
+        let line = captures.get(5).and_then(|line| line.as_str().parse::<u32>().ok());
+        let (prefix, method, suffix) = (&captures[1], &captures[3], &captures[6]);
+
+        let methods = match class.methods.get(method) {
+            Some(methods) => methods.iter().filter(|method| match line {
+                Some(line) => method.contains(line),
+                None => true,
+            }).collect::<Vec<_>>(),
+            None => Vec::new(),
+        };
+
+        let mut result = Vec::new();
+        if methods.is_empty() {
+            let file = self.source_file(&class.name);
+            result.push(match line {
+                Some(line) => format!("{}{}.{}({}:{}){}", prefix, class.name, method, file, line, suffix),
+                None => format!("{}{}.{}({}){}", prefix, class.name, method, file, suffix),
+            });
+        }
+
+        for method in methods {
+            //Inlined methods are qualified with their class.
+            let (class_name, name) = match method.name.rfind('.') {
+                Some(idx) => (&method.name[..idx], &method.name[idx + 1..]),
+                None => (class.name.as_str(), method.name.as_str()),
+            };
+            let file = self.source_file(class_name);
+            let frame = match line {
+                Some(line) => format!("{}{}.{}({}:{}){}", prefix, class_name, name, file, method.original_line(line), suffix),
+                None => format!("{}{}.{}({}){}", prefix, class_name, name, file, suffix),
+            };
+
+            //Without line number overloads cannot be distinguished.
+            if !result.contains(&frame) {
+                result.push(frame);
+            }
+        }
+
+        Some(result)
+    }
+
+    //Returns whether message is exception line, e.g. `a.b: message` or `Caused by: a.b`
+    fn is_exception(&self, msg: &str) -> bool {
+        let msg = msg.trim_start();
+        let msg = msg.strip_prefix("Caused by: ").or_else(|| msg.strip_prefix("Suppressed: ")).unwrap_or(msg);
+        match self.class.find(msg) {
+            Some(found) if found.start() == 0 => msg[found.end()..].is_empty() || msg[found.end()..].starts_with(':'),
+            _ => false,
+        }
+    }
+
+    ///Retraces message, returning none if it has nothing to retrace.
+    ///
+    ///Stack frame can be expanded into multiple frames when it includes inlined methods.
+    ///Class names are replaced only within exception lines, as any other dotted text could be mistaken for class.
+    pub fn retrace(&self, msg: &str) -> Option<Vec<String>> {
+        if let Some(frames) = self.retrace_frame(msg) {
+            return Some(frames);
+        }
+        if !self.is_exception(msg) {
+            return None;
+        }
+
+        let mut result = String::new();
+        let mut last = 0;
+        for found in self.class.find_iter(msg) {
+            if let Some(class) = self.class(found.as_str()) {
+                result.push_str(&msg[last..found.start()]);
+                result.push_str(class);
+                last = found.end();
+            }
+        }
+
+        match last {
+            0 => None,
+            last => {
+                result.push_str(&msg[last..]);
+                Some(vec![result])
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Mapping;
+
+    const MAPPING: &str = "# compiler: R8
+com.foo.MainActivity -> a.a:
+# {\"id\":\"sourceFile\",\"fileName\":\"MainActivity.kt\"}
+    int counter -> a
+    1:1:void <init>():10:10 -> <init>
+    1:3:void onClick(android.view.View):20:22 -> a
+    4:4:void com.foo.Helper.check(int):42:42 -> a
+    4:4:void onClick(android.view.View):23 -> a
+    5:5:void onCreate(android.os.Bundle):30:30 -> b
+com.foo.Helper -> a.b:
+    void check(int) -> a
+com.foo.MyException -> a.c:
+";
+
+    #[test]
+    fn should_retrace_frames() {
+        let mapping = Mapping::parse(MAPPING);
+        assert!(!mapping.is_empty());
+        assert_eq!(mapping.class("a.b"), Some("com.foo.Helper"));
+
+        assert_eq!(mapping.retrace("\tat a.a.a(Unknown Source:2)").unwrap(), ["\tat com.foo.MainActivity.onClick(MainActivity.kt:21)"]);
+        assert_eq!(mapping.retrace("\tat a.a.a(SourceFile:4)").unwrap(), [
+            "\tat com.foo.Helper.check(Helper.java:42)",
+            "\tat com.foo.MainActivity.onClick(MainActivity.kt:23)",
+        ]);
+        assert_eq!(mapping.retrace("\tat a.b.a(Unknown Source)").unwrap(), ["\tat com.foo.Helper.check(Helper.java)"]);
+        assert!(mapping.retrace("\tat android.app.Activity.performCreate(Activity.java:8000)").is_none());
+    }
+
+    #[test]
+    fn should_retrace_class_names() {
+        let mapping = Mapping::parse(MAPPING);
+        assert_eq!(mapping.retrace("Caused by: a.c: failed in a.b").unwrap(), ["Caused by: com.foo.MyException: failed in com.foo.Helper"]);
+        assert!(mapping.retrace("java.lang.IllegalStateException: a.d").is_none());
+        assert_eq!(mapping.retrace("java.lang.IllegalStateException: a.b").unwrap(), ["java.lang.IllegalStateException: com.foo.Helper"]);
+        assert_eq!(mapping.retrace("a.c").unwrap(), ["com.foo.MyException"]);
+        assert!(mapping.retrace("connecting to a.b").is_none());
+        assert!(mapping.retrace("version a.a: 1.2").is_none());
+    }
+}
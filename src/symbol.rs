@@ -0,0 +1,217 @@
+use crate::crash::NativeFrame;
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const DEFAULT_TOOLS: [&str; 2] = ["llvm-addr2line", "addr2line"];
+//Directory of library on device, mapped to ABI directory of NDK build.
+const ABI_DIRS: [(&str, &str); 4] = [
+    ("/arm64/", "arm64-v8a"),
+    ("/arm/", "armeabi-v7a"),
+    ("/x86_64/", "x86_64"),
+    ("/x86/", "x86"),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+///Source location of native code.
+pub struct Location {
+    pub function: String,
+    ///Empty if unknown.
+    pub file: String,
+    ///Zero if unknown.
+    pub line: u32,
+}
+
+///Parses output of `addr2line -f -i -C` for single address.
+///
+///Inlined functions are reported first, followed by the function they are inlined into.
+pub fn parse_output(output: &str) -> Vec<Location> {
+    let mut result = Vec::new();
+    let mut lines = output.lines();
+
+    while let (Some(function), Some(location)) = (lines.next(), lines.next()) {
+        let (file, line) = match location.rfind(':') {
+            Some(idx) => (&location[..idx], location[idx + 1..].split_whitespace().next().unwrap_or("")),
+            None => (location, ""),
+        };
+        //Location can be reported with column, i.e. `file:line:column`
+        let (file, line) = match file.rfind(':') {
+            Some(idx) if file[idx + 1..].parse::<u32>().is_ok() => (&file[..idx], &file[idx + 1..]),
+            _ => (file, line),
+        };
+        let file = match file {
+            "??" | "" => "",
+            file => file,
+        };
+
+        if function == "??" && file.is_empty() {
+            continue;
+        }
+
+        result.push(Location {
+            function: function.to_owned(),
+            file: file.to_owned(),
+            line: line.parse().unwrap_or(0),
+        });
+    }
+
+    result
+}
+
+///Returns file name of library, handling libraries loaded directly from APK (e.g. `base.apk!libfoo.so`).
+pub fn library_name(library: &str) -> &str {
+    let name = match library.rfind('/') {
+        Some(idx) => &library[idx + 1..],
+        None => library,
+    };
+
+    match name.rfind('!') {
+        Some(idx) => &name[idx + 1..],
+        None => name,
+    }
+}
+
+///Resolves native frames into source locations, using unstripped libraries and local `addr2line`.
+pub struct Symbolizer {
+    dir: PathBuf,
+    tool: Option<String>,
+    cache: HashMap<(String, u64), Vec<Location>>,
+}
+
+impl Symbolizer {
+    ///Creates new instance, looking up libraries in `dir`.
+    ///
+    ///If `tool` is not specified, `llvm-addr2line` is used, falling back to `addr2line`.
+    pub fn new(dir: PathBuf, tool: Option<String>) -> Self {
+        Self {
+            dir,
+            tool,
+            cache: HashMap::new(),
+        }
+    }
+
+    ///Finds library within directory or within its ABI sub-directories.
+    fn find_library(&self, library: &str) -> Option<PathBuf> {
+        let name = library_name(library);
+        if name.is_empty() {
+            return None;
+        }
+
+        let path = self.dir.join(name);
+        if path.is_file() {
+            return Some(path);
+        }
+
+        for (device_dir, abi) in ABI_DIRS.iter() {
+            if library.contains(device_dir) {
+                let path = self.dir.join(abi).join(name);
+                if path.is_file() {
+                    return Some(path);
+                }
+            }
+        }
+
+        let entries = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(_) => return None,
+        };
+
+        let mut dirs = entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()).filter(|path| path.is_dir()).collect::<Vec<_>>();
+        dirs.sort();
+        dirs.into_iter().map(|dir| dir.join(name)).find(|path| path.is_file())
+    }
+
+    fn run_tool(tool: &str, library: &Path, pc: u64) -> std::io::Result<std::process::Output> {
+        Command::new(tool).arg("-f").arg("-i").arg("-C").arg("-e").arg(library).arg(format!("0x{:x}", pc)).output()
+    }
+
+    fn run(&mut self, library: &Path, pc: u64) -> Vec<Location> {
+        let output = match self.tool.as_ref() {
+            Some(tool) => Self::run_tool(tool, library, pc),
+            None => {
+                let mut result = Err(std::io::Error::new(std::io::ErrorKind::NotFound, "addr2line is not found"));
+                for tool in DEFAULT_TOOLS.iter() {
+                    result = Self::run_tool(tool, library, pc);
+                    match result.as_ref() {
+                        Err(error) if error.kind() == std::io::ErrorKind::NotFound => continue,
+                        _ => {
+                            self.tool = Some(tool.to_string());
+                            break;
+                        }
+                    }
+                }
+                result
+            }
+        };
+
+        match output {
+            Ok(output) if output.status.success() => parse_output(&String::from_utf8_lossy(&output.stdout)),
+            Ok(output) => {
+                eprintln!("addr2line failed on '{}': {}", library.display(), String::from_utf8_lossy(&output.stderr).trim());
+                Vec::new()
+            },
+            Err(error) => {
+                eprintln!("Failed to run addr2line: {}", error);
+                Vec::new()
+            }
+        }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn insert(&mut self, library: &str, pc: u64, locations: Vec<Location>) {
+        self.cache.insert((library.to_owned(), pc), locations);
+    }
+
+    ///Returns source locations of frame, if its library is available.
+    pub fn resolve(&mut self, frame: &NativeFrame) -> Vec<Location> {
+        let key = (frame.library.clone(), frame.pc);
+        if let Some(locations) = self.cache.get(&key) {
+            return locations.clone();
+        }
+
+        let locations = match self.find_library(&frame.library) {
+            Some(library) => self.run(&library, frame.pc),
+            None => Vec::new(),
+        };
+        self.cache.insert(key, locations.clone());
+        locations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_output, library_name, Location};
+
+    #[test]
+    fn should_parse_addr2line_output() {
+        let output = "inner(int)\n/src/foo.cpp:1:27\nfoo(int)\n/src/foo.cpp:2 (discriminator 1)\n??\n??:0\n";
+        assert_eq!(parse_output(output), [
+            Location {
+                function: "inner(int)".to_owned(),
+                file: "/src/foo.cpp".to_owned(),
+                line: 1,
+            },
+            Location {
+                function: "foo(int)".to_owned(),
+                file: "/src/foo.cpp".to_owned(),
+                line: 2,
+            },
+        ]);
+
+        assert_eq!(parse_output("foo\n??:?\n"), [
+            Location {
+                function: "foo".to_owned(),
+                file: String::new(),
+                line: 0,
+            },
+        ]);
+    }
+
+    #[test]
+    fn should_get_library_name() {
+        assert_eq!(library_name("/data/app/com.foo/lib/arm64/libfoo.so"), "libfoo.so");
+        assert_eq!(library_name("/data/app/com.foo/base.apk!libfoo.so"), "libfoo.so");
+        assert_eq!(library_name("libfoo.so"), "libfoo.so");
+    }
+}
@@ -0,0 +1,115 @@
+use std::collections::HashSet;
+
+#[derive(Debug, Clone)]
+///Tag pattern, which can be:
+///
+///- Exact name, e.g. `chromium`;
+///- Glob with `*` and `?` wildcards, e.g. `Camera*`;
+///- Regex enclosed in slashes, e.g. `/^cr_/`.
+pub enum TagPattern {
+    Exact(String),
+    Pattern(regex::Regex),
+}
+
+impl TagPattern {
+    fn glob_to_regex(glob: &str) -> String {
+        let mut result = String::with_capacity(glob.len() + 2);
+        result.push('^');
+
+        let mut buffer = [0; 4];
+        for ch in glob.chars() {
+            match ch {
+                '*' => result.push_str(".*"),
+                '?' => result.push('.'),
+                ch => result.push_str(&regex::escape(ch.encode_utf8(&mut buffer))),
+            }
+        }
+
+        result.push('$');
+        result
+    }
+}
+
+impl core::str::FromStr for TagPattern {
+    type Err = regex::Error;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        if let Some(regex) = text.strip_prefix('/').and_then(|text| text.strip_suffix('/')) {
+            regex::Regex::new(regex).map(TagPattern::Pattern)
+        } else if text.contains(|ch| ch == '*' || ch == '?') {
+            regex::Regex::new(&Self::glob_to_regex(text)).map(TagPattern::Pattern)
+        } else {
+            Ok(TagPattern::Exact(text.to_owned()))
+        }
+    }
+}
+
+#[derive(Default)]
+///Set of tags, looking up exact names first and falling back to patterns.
+pub struct TagSet<'a> {
+    exact: HashSet<&'a str>,
+    patterns: Vec<regex::Regex>,
+}
+
+impl<'a> TagSet<'a> {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.exact.is_empty() && self.patterns.is_empty()
+    }
+
+    #[inline]
+    pub fn insert(&mut self, pattern: &'a TagPattern) {
+        match pattern {
+            TagPattern::Exact(tag) => {
+                self.exact.insert(tag.as_str());
+            },
+            TagPattern::Pattern(regex) => self.patterns.push(regex.clone()),
+        }
+    }
+
+    pub fn contains(&self, tag: &str) -> bool {
+        self.exact.contains(tag) || self.patterns.iter().any(|pattern| pattern.is_match(tag))
+    }
+}
+
+impl<'a> core::iter::FromIterator<&'a TagPattern> for TagSet<'a> {
+    fn from_iter<I: IntoIterator<Item = &'a TagPattern>>(iter: I) -> Self {
+        let mut result = Self::new();
+        for pattern in iter {
+            result.insert(pattern);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TagPattern, TagSet};
+
+    #[test]
+    fn should_match_tag_patterns() {
+        let patterns = ["chromium", "Camera*", "/^cr_/", "Wi?i", "a.b"].iter().map(|pattern| pattern.parse::<TagPattern>().expect("To parse")).collect::<Vec<_>>();
+        let set = patterns.iter().collect::<TagSet>();
+
+        assert!(set.contains("chromium"));
+        assert!(!set.contains("chromium2"));
+        assert!(set.contains("Camera"));
+        assert!(set.contains("CameraService"));
+        assert!(!set.contains("MyCamera"));
+        assert!(set.contains("cr_Foo"));
+        assert!(!set.contains("acr_Foo"));
+        assert!(set.contains("Wifi"));
+        assert!(set.contains("a.b"));
+        assert!(!set.contains("axb"));
+    }
+
+    #[test]
+    fn should_reject_invalid_regex() {
+        assert!("/(/".parse::<TagPattern>().is_err());
+    }
+}
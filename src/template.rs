@@ -0,0 +1,202 @@
+use core::fmt;
+
+#[derive(Debug)]
+///Template parse error.
+pub struct ParseError(String);
+
+impl fmt::Display for ParseError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+macro_rules! error {
+    ($($arg:tt)*) => {
+        return Err(ParseError(format!($($arg)*)))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///Line field to output.
+pub enum Field {
+    Date,
+    Time,
+    Level,
+    Tag,
+    Pid,
+    Tid,
+    Uid,
+    Msg,
+    Serial,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Align {
+    Left,
+    Right,
+    Center,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Color {
+    ///Uses the same color as default output (i.e. tag and level colors).
+    Auto,
+    Fixed(termcolor::Color),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+///Template part.
+pub enum Part {
+    Literal(String),
+    Field {
+        field: Field,
+        align: Align,
+        width: usize,
+        color: Option<Color>,
+    },
+}
+
+#[derive(Debug, Clone)]
+///Output template, consisting of literal text and fields in format `{field[:[align]width][!color]}`.
+///
+///Fields: `date`, `time`, `level`, `tag`, `pid`, `tid`, `uid`, `msg` and `serial`.
+///
+///Alignment: `<` (left, default), `>` (right) or `^` (center).
+///
+///Color: `auto` (default colors of tag and level) or any color name, ANSI 256 code or `r,g,b`.
+///
+///Use `{{` and `}}` to output braces.
+///
+///Example: `{time} {pid:>5} {level!auto} {tag:<20!auto} {msg}`
+pub struct Template {
+    pub parts: Vec<Part>,
+}
+
+fn parse_field(spec: &str) -> Result<Part, ParseError> {
+    let (spec, color) = match spec.find('!') {
+        Some(idx) => match &spec[idx + 1..] {
+            "auto" => (&spec[..idx], Some(Color::Auto)),
+            color => match color.parse() {
+                Ok(color) => (&spec[..idx], Some(Color::Fixed(color))),
+                Err(_) => error!("Invalid color '{}'", color),
+            },
+        },
+        None => (spec, None),
+    };
+
+    let (name, format) = match spec.find(':') {
+        Some(idx) => (&spec[..idx], &spec[idx + 1..]),
+        None => (spec, ""),
+    };
+
+    let field = match name {
+        "date" => Field::Date,
+        "time" => Field::Time,
+        "level" => Field::Level,
+        "tag" => Field::Tag,
+        "pid" => Field::Pid,
+        "tid" => Field::Tid,
+        "uid" => Field::Uid,
+        "msg" | "message" => Field::Msg,
+        "serial" => Field::Serial,
+        name => error!("Unknown field '{}'", name),
+    };
+
+    let (align, width) = match format.as_bytes().first() {
+        Some(b'<') => (Align::Left, &format[1..]),
+        Some(b'>') => (Align::Right, &format[1..]),
+        Some(b'^') => (Align::Center, &format[1..]),
+        _ => (Align::Left, format),
+    };
+
+    let width = match width {
+        "" => 0,
+        width => match width.parse() {
+            Ok(width) => width,
+            Err(_) => error!("Invalid width '{}' of '{}'", width, name),
+        },
+    };
+
+    Ok(Part::Field {
+        field,
+        align,
+        width,
+        color,
+    })
+}
+
+impl core::str::FromStr for Template {
+    type Err = ParseError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut cursor = text;
+
+        while let Some(idx) = cursor.find(|ch| ch == '{' || ch == '}') {
+            literal.push_str(&cursor[..idx]);
+            let rest = &cursor[idx..];
+
+            if rest.starts_with("{{") || rest.starts_with("}}") {
+                literal.push_str(&rest[..1]);
+                cursor = &rest[2..];
+            } else if rest.starts_with('}') {
+                error!("Unmatched '}}'");
+            } else {
+                let end = match rest.find('}') {
+                    Some(end) => end,
+                    None => error!("Unmatched '{{'"),
+                };
+
+                if !literal.is_empty() {
+                    parts.push(Part::Literal(core::mem::take(&mut literal)));
+                }
+                parts.push(parse_field(&rest[1..end])?);
+                cursor = &rest[end + 1..];
+            }
+        }
+
+        literal.push_str(cursor);
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+
+        Ok(Self {
+            parts
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Template, Part, Field, Align, Color};
+
+    #[test]
+    fn should_parse_template() {
+        let template: Template = "{time} {pid:>5} {level!auto} {tag:<20!blue} {{{msg}}}".parse().expect("To parse");
+        assert_eq!(template.parts, [
+            Part::Field { field: Field::Time, align: Align::Left, width: 0, color: None },
+            Part::Literal(" ".to_owned()),
+            Part::Field { field: Field::Pid, align: Align::Right, width: 5, color: None },
+            Part::Literal(" ".to_owned()),
+            Part::Field { field: Field::Level, align: Align::Left, width: 0, color: Some(Color::Auto) },
+            Part::Literal(" ".to_owned()),
+            Part::Field { field: Field::Tag, align: Align::Left, width: 20, color: Some(Color::Fixed(termcolor::Color::Blue)) },
+            Part::Literal(" {".to_owned()),
+            Part::Field { field: Field::Msg, align: Align::Left, width: 0, color: None },
+            Part::Literal("}".to_owned()),
+        ]);
+    }
+
+    #[test]
+    fn should_reject_invalid_template() {
+        assert!("{time".parse::<Template>().is_err());
+        assert!("time}".parse::<Template>().is_err());
+        assert!("{name}".parse::<Template>().is_err());
+        assert!("{pid:>x}".parse::<Template>().is_err());
+        assert!("{pid!nope}".parse::<Template>().is_err());
+    }
+}